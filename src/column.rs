@@ -4,6 +4,8 @@ use crate::event::*;
 use crate::prelude_lib::*;
 use std::hint::unreachable_unchecked;
 use crate::linkage::LiftColumn;
+use crate::kernel::Chunk;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -56,6 +58,8 @@ where
     pub col: &'a mut Column<M, T>,
     must_log: bool,
     log: &'a mut Vec<(Id<M>, T)>,
+    /// Replaces `log` once `edit_unordered` is used.
+    unordered: &'a mut Option<HashMap<Id<M>, T>>,
 }
 pub struct WriteColumn<'a, M: TableMarker, T: AnyDebug> {
     pub col: MutButRef<'a, Column<M, T>>,
@@ -109,6 +113,9 @@ where
     fn index(&self, i: I) -> &T {
         unsafe {
             let i = i.check_from_capacity(PhantomData, self.col.data.len());
+            if let Some(unordered) = &*self.unordered {
+                return unordered.get(&i.uncheck()).unwrap_or_else(|| self.col.data.get_unchecked(i.to_usize()));
+            }
             if let Some((prev, dude)) = self.log.last() {
                 match i.uncheck().cmp(prev) {
                     Ordering::Less => disordered_column_access(),
@@ -133,6 +140,10 @@ where
             if !self.must_log {
                 return self.col.data.get_unchecked_mut(i.to_usize());
             }
+            if let Some(unordered) = self.unordered {
+                let data = &self.col.data;
+                return unordered.entry(i).or_insert_with(|| data.get_unchecked(i.to_usize()).clone());
+            }
             let prev = self.log.last().map(|(i, _)| i);
             let prev = prev.map(|prev| i.cmp(prev));
             let prev = prev.unwrap_or(Ordering::Greater);
@@ -166,19 +177,174 @@ where
 }
 // WriteColumn is append-only, so IndexMut is not provided.
 
+impl<'a, M: TableMarker, T: AnyDebug> ReadColumn<'a, M, T> {
+    /// Iterate over every `(id, value)` in the column, without needing the `Ids`. Rows that have
+    /// been removed are *not* skipped; their values linger until the id is recycled.
+    /// ```
+    /// # use v9::prelude::*;
+    /// #[v9::table]
+    /// pub struct items {
+    ///     pub price: u32,
+    /// }
+    /// # fn main() {
+    /// let mut universe = Universe::new();
+    /// items::Marker::register(&mut universe);
+    /// universe.eval(|mut items: items::Write| {
+    ///     for price in 1..=4 {
+    ///         items.push(items::Row { price });
+    ///     }
+    /// });
+    /// let total = universe.eval(|prices: items::read::price| {
+    ///     prices.iter().map(|(_id, price)| price).sum::<u32>()
+    /// });
+    /// assert_eq!(total, 10);
+    /// # }
+    /// ```
+    pub fn iter(&self) -> impl ExactSizeIterator<Item=(Id<M>, &T)> + DoubleEndedIterator + '_ {
+        self.col.data.iter().enumerate().map(|(i, v)| (Id::from_usize(i), v))
+    }
+    /// Like indexing, but gives `None` instead of panicking if `i` is past the end of the column.
+    /// (Like `iter`, the values of removed rows are still there.)
+    pub fn get(&self, i: impl Check<M=M>) -> Option<&T> {
+        self.col.data.get(i.to_usize())
+    }
+    /// The whole column, indexed by `Id::to_usize()`, eg for handing to a vectorized routine.
+    /// Like `iter`, this includes the slots of removed rows; if that matters, consult the
+    /// `IdList`, or only use this on tables without holes (like `#[dense]` ones).
+    pub fn as_slice(&self) -> &[T] {
+        &self.col.data
+    }
+    /// Bucket the live rows by their value. Each bucket's ids are in order.
+    pub fn group_by(&self, ids: &IdList<M>) -> HashMap<&T, Vec<Id<M>>>
+    where
+        T: Eq + std::hash::Hash,
+    {
+        let mut groups = HashMap::<&T, Vec<Id<M>>>::new();
+        for id in ids {
+            groups.entry(&self[id]).or_default().push(id.uncheck());
+        }
+        groups
+    }
+}
+impl<'a, M: TableMarker, T: AnyDebug> FastEditColumn<'a, M, T> {
+    /// See `ReadColumn::get`.
+    pub fn get(&self, i: impl Check<M=M>) -> Option<&T> {
+        self.col.data.get(i.to_usize())
+    }
+    /// Like indexing, but gives `None` instead of panicking if `i` is past the end of the column.
+    pub fn get_mut(&mut self, i: impl Check<M=M>) -> Option<&mut T> {
+        self.col.data.get_mut(i.to_usize())
+    }
+    /// See `ReadColumn::as_slice`. The column's length can't be changed through this.
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.col.data
+    }
+    /// Like `ReadColumn::iter`, including rows that have been removed.
+    pub fn iter_mut(&mut self) -> impl ExactSizeIterator<Item=(Id<M>, &mut T)> + DoubleEndedIterator + '_ {
+        self.col.data.iter_mut().enumerate().map(|(i, v)| (Id::from_usize(i), v))
+    }
+    /// Call `f` on every value. Like `iter_mut`, this includes rows that have been removed.
+    pub fn map_in_place(&mut self, mut f: impl FnMut(Id<M>, &mut T)) {
+        for (id, v) in self.iter_mut() {
+            f(id, v);
+        }
+    }
+    /// Set every value, including those of removed rows, to `value`.
+    pub fn fill(&mut self, value: T)
+    where
+        T: Clone,
+    {
+        for v in &mut self.col.data {
+            *v = value.clone();
+        }
+    }
+}
+unsafe impl<'a, M: TableMarker, T: AnyDebug + Sync> Chunk<M> for ReadColumn<'a, M, T> {
+    type Whole = Self;
+    unsafe fn chunk(whole: &mut Self, _range: IdRange<'static, Id<M>>) -> Self {
+        ReadColumn { col: whole.col }
+    }
+}
+/// The part of a `FastEditColumn` given to one of `Universe::run_par`'s threads. Indexing outside
+/// of its range panics.
+pub struct EditChunk<'a, M: TableMarker, T: AnyDebug> {
+    data: *mut T,
+    range: IdRange<'static, Id<M>>,
+    _col: PhantomData<&'a mut Column<M, T>>,
+}
+unsafe impl<'a, M: TableMarker, T: AnyDebug + Send> Send for EditChunk<'a, M, T> {}
+impl<'a, M: TableMarker, T: AnyDebug> EditChunk<'a, M, T> {
+    pub fn range(&self) -> IdRange<'static, Id<M>> { self.range }
+    fn offset(&self, i: impl Check<M = M>) -> usize {
+        if !self.range.contains(Id(i.to_raw())) {
+            panic!("{:?} is outside of this chunk, {:?}", Id::<M>(i.to_raw()), self.range);
+        }
+        i.to_usize()
+    }
+}
+impl<'a, M: TableMarker, T: AnyDebug, I: Check<M = M>> Index<I> for EditChunk<'a, M, T> {
+    type Output = T;
+    fn index(&self, i: I) -> &T {
+        unsafe { &*self.data.add(self.offset(i)) }
+    }
+}
+impl<'a, M: TableMarker, T: AnyDebug, I: Check<M = M>> IndexMut<I> for EditChunk<'a, M, T> {
+    fn index_mut(&mut self, i: I) -> &mut T {
+        unsafe { &mut *self.data.add(self.offset(i)) }
+    }
+}
+unsafe impl<'a, M: TableMarker, T: AnyDebug + Send> Chunk<M> for EditChunk<'a, M, T> {
+    type Whole = FastEditColumn<'a, M, T>;
+    unsafe fn chunk(whole: &mut Self::Whole, range: IdRange<'static, Id<M>>) -> Self {
+        assert!(range.end.to_usize() <= whole.col.data.len());
+        EditChunk {
+            data: whole.col.data.as_mut_ptr(),
+            range,
+            _col: PhantomData,
+        }
+    }
+}
 impl<'a, M: TableMarker, T: AnyDebug> WriteColumn<'a, M, T> {
     pub fn borrow(&self) -> ReadColumn<M, T> {
         ReadColumn { col: &*self.col }
     }
+    /// Append the values of `iter` to the end of the column, giving how many there were. This
+    /// doesn't push any rows; it's for filling in columns one at a time, eg when loading SOA
+    /// data. It's up to you to keep the table's columns the same length, and to then push the ids
+    /// for the new rows, all within the same kernel; in debug builds, a kernel that leaves the
+    /// lengths different panics.
+    pub fn extend_from(&mut self, iter: impl Iterator<Item=T>) -> usize {
+        // A column that's longer than its ids is harmless; a shorter one isn't, but we only grow.
+        let data = unsafe { self.col.get_mut().data_mut() };
+        let before = data.len();
+        data.extend(iter);
+        data.len() - before
+    }
 }
 impl<'a, M: TableMarker, T: AnyDebug> EditColumn<'a, M, T>
 where
     T: Clone,
 {
     pub fn borrow(&self) -> ReadColumn<M, T> {
-        assert!(self.log.is_empty());
+        assert!(self.log.is_empty() && self.unordered.is_none());
         ReadColumn { col: &*self.col }
     }
+    /// Edit rows in any order, unlike indexing, which must go in increasing order. Once this is
+    /// used the column's edits are kept in a `HashMap` rather than a `Vec`, so every access after
+    /// it is somewhat slower.
+    pub fn edit_unordered<I: Check<M = M>>(&mut self, i: I, f: impl FnOnce(&mut T)) {
+        unsafe {
+            let i = i.check_from_capacity(PhantomData, self.col.data.len());
+            if !self.must_log {
+                f(self.col.data.get_unchecked_mut(i.to_usize()));
+                return;
+            }
+            let log = &mut *self.log;
+            let unordered = self.unordered.get_or_insert_with(|| log.drain(..).collect());
+            let data = &self.col.data;
+            f(unordered.entry(i.uncheck()).or_insert_with(|| data.get_unchecked(i.to_usize()).clone()));
+        }
+    }
 }
 
 unsafe impl<'a, M, T: AnyDebug> ExtractOwned for ReadColumn<'a, M, T>
@@ -215,6 +381,7 @@ pub struct EditColumnOwned<'a, M: TableMarker, T: AnyDebug> {
     col: &'a mut Column<M, T>,
     must_log: bool,
     log: Vec<(Id<M>, T)>,
+    unordered: Option<HashMap<Id<M>, T>>,
 }
 unsafe impl<'a, M, T> Extract for EditColumn<'a, M, T>
 where
@@ -231,11 +398,11 @@ where
         let col: &mut Column<M, T> = rez.take_mut_downcast();
         let must_log = universe.is_tracked::<Edit<M, T>>();
         let log = vec![];
-        EditColumnOwned { col, must_log, log }
+        EditColumnOwned { col, must_log, log, unordered: None }
     }
     unsafe fn convert(_universe: &Universe, owned: *mut Self::Owned) -> Self {
-        let EditColumnOwned { col, must_log, log } = &mut *owned;
-        EditColumn { col, must_log: *must_log, log }
+        let EditColumnOwned { col, must_log, log, unordered } = &mut *owned;
+        EditColumn { col, must_log: *must_log, log, unordered }
     }
     type Cleanup = EditColumnCleanup<M, T>;
 }
@@ -253,9 +420,17 @@ where
     // or `EditColumn<>: Extract`?
 {
     fn pre_cleanup(eco: EditColumnOwned<'a, M, T>, _universe: &Universe) -> Self {
+        let log = match eco.unordered {
+            None => eco.log,
+            Some(unordered) => {
+                let mut log: Vec<_> = unordered.into_iter().collect();
+                log.sort_unstable_by_key(|&(id, _)| id);
+                log
+            },
+        };
         Self {
             must_log: eco.must_log,
-            log: eco.log,
+            log,
         }
     }
     fn post_cleanup(self, universe: &Universe) {
@@ -316,3 +491,205 @@ unsafe impl<'a, M: TableMarker, T: AnyDebug> ColumnInfo<M> for WriteColumn<'a, M
         self.col.data.len()
     }
 }
+
+/// A column that only has values for a few rows, kept in a `BTreeMap`. It isn't part of the
+/// table's `Row`; add it with `Universe::add_sparse_column::<M, T>()`, which also removes a row's
+/// entry when the row is removed. Kernels take it as `&SparseColumn<M, T>` or
+/// `&mut SparseColumn<M, T>`.
+#[derive(Debug)]
+pub struct SparseColumn<M: TableMarker, T: AnyDebug> {
+    map: BTreeMap<Id<M>, T>,
+}
+impl<M: TableMarker, T: AnyDebug> Default for SparseColumn<M, T> {
+    fn default() -> Self {
+        SparseColumn { map: BTreeMap::new() }
+    }
+}
+impl<M: TableMarker, T: AnyDebug> SparseColumn<M, T> {
+    pub fn new() -> Self { Self::default() }
+    pub fn get(&self, i: impl Check<M = M>) -> Option<&T> { self.map.get(&Id(i.to_raw())) }
+    pub fn get_mut(&mut self, i: impl Check<M = M>) -> Option<&mut T> { self.map.get_mut(&Id(i.to_raw())) }
+    pub fn contains(&self, i: impl Check<M = M>) -> bool { self.map.contains_key(&Id(i.to_raw())) }
+    /// Returns the previous value.
+    pub fn insert(&mut self, i: impl Check<M = M>, val: T) -> Option<T> { self.map.insert(Id(i.to_raw()), val) }
+    pub fn remove(&mut self, i: impl Check<M = M>) -> Option<T> { self.map.remove(&Id(i.to_raw())) }
+    /// The number of rows that have a value.
+    pub fn len(&self) -> usize { self.map.len() }
+    pub fn is_empty(&self) -> bool { self.map.is_empty() }
+    /// Iterate over the rows that have a value, in order.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item=(Id<M>, &T)> + ExactSizeIterator + '_ {
+        self.map.iter().map(|(&id, val)| (id, val))
+    }
+    pub fn iter_mut(&mut self) -> impl DoubleEndedIterator<Item=(Id<M>, &mut T)> + ExactSizeIterator + '_ {
+        self.map.iter_mut().map(|(&id, val)| (id, val))
+    }
+}
+impl<M: TableMarker, T: AnyDebug, I: Check<M = M>> Index<I> for SparseColumn<M, T> {
+    type Output = T;
+    fn index(&self, i: I) -> &T {
+        let id = Id(i.to_raw());
+        self.map.get(&id).unwrap_or_else(|| panic!("{}: no value for {:?}", type_name::<Self>(), id))
+    }
+}
+impl<M: TableMarker, T: AnyDebug, I: Check<M = M>> IndexMut<I> for SparseColumn<M, T> {
+    fn index_mut(&mut self, i: I) -> &mut T {
+        let id = Id(i.to_raw());
+        self.map.get_mut(&id).unwrap_or_else(|| panic!("{}: no value for {:?}", type_name::<Self>(), id))
+    }
+}
+unsafe impl<'a, M: TableMarker, T: AnyDebug> Extract for &'a SparseColumn<M, T> {
+    fn each_resource(f: &mut dyn FnMut(Ty, Access)) {
+        f(Ty::of::<SparseColumn<M, T>>(), Access::Read)
+    }
+    type Owned = Self;
+    unsafe fn extract(_universe: &Universe, rez: &mut Rez) -> Self::Owned {
+        rez.take_ref_downcast()
+    }
+    unsafe fn convert(_universe: &Universe, owned: *mut Self::Owned) -> Self {
+        *owned
+    }
+    type Cleanup = ();
+}
+unsafe impl<'a, M: TableMarker, T: AnyDebug> Extract for &'a mut SparseColumn<M, T> {
+    fn each_resource(f: &mut dyn FnMut(Ty, Access)) {
+        f(Ty::of::<SparseColumn<M, T>>(), Access::Write)
+    }
+    type Owned = Self;
+    unsafe fn extract(_universe: &Universe, rez: &mut Rez) -> Self::Owned {
+        rez.take_mut_downcast()
+    }
+    unsafe fn convert(_universe: &Universe, owned: *mut Self::Owned) -> Self {
+        *owned
+    }
+    type Cleanup = ();
+}
+
+/// A read-only, run-length encoded copy of a `Column`, for cold data that has long runs of
+/// equal values.
+///
+/// The rows are compressed in blocks of `BLOCK` rows. A block is decompressed the first time
+/// one of its rows is read, and stays cached until `evict`.
+///
+/// A table's columns are always a `Column`, so this can't stand in for one. Instead, `compress` a
+/// column that won't be changed for a while and add the result to the universe with `add_mut`;
+/// kernels take it as `&CompressedColumn<M, T>`. It can't be taken mutably. To change it,
+/// `decompress` it and compress it again.
+#[cfg(feature = "compressed_column")]
+#[derive(Debug, Clone)]
+pub struct CompressedColumn<M: TableMarker, T: AnyDebug> {
+    table_marker: M,
+    len: usize,
+    blocks: Vec<CompressedBlock<T>>,
+}
+#[cfg(feature = "compressed_column")]
+#[derive(Debug, Clone)]
+struct CompressedBlock<T> {
+    /// Each run's value, and the index within the block just past its end.
+    runs: Vec<(usize, T)>,
+    cache: std::sync::OnceLock<Vec<T>>,
+}
+#[cfg(feature = "compressed_column")]
+impl<T: Clone> CompressedBlock<T> {
+    fn decode(&self) -> Vec<T> {
+        let mut data = Vec::with_capacity(self.runs.last().map_or(0, |&(end, _)| end));
+        let mut start = 0;
+        for (end, val) in &self.runs {
+            data.extend(std::iter::repeat(val).take(end - start).cloned());
+            start = *end;
+        }
+        data
+    }
+    fn cached(&self) -> &[T] {
+        self.cache.get_or_init(|| self.decode())
+    }
+}
+#[cfg(feature = "compressed_column")]
+impl<M: TableMarker, T: AnyDebug + Clone> CompressedColumn<M, T> {
+    /// The number of rows in each block.
+    pub const BLOCK: usize = 1024;
+    pub fn compress(col: &Column<M, T>) -> Self
+    where
+        T: PartialEq,
+    {
+        let blocks = col.data().chunks(Self::BLOCK).map(|chunk| {
+            let mut runs: Vec<(usize, T)> = vec![];
+            for (i, val) in chunk.iter().enumerate() {
+                match runs.last_mut() {
+                    Some((end, prev)) if prev == val => *end = i + 1,
+                    _ => runs.push((i + 1, val.clone())),
+                }
+            }
+            runs.shrink_to_fit();
+            CompressedBlock {
+                runs,
+                cache: Default::default(),
+            }
+        }).collect();
+        CompressedColumn {
+            table_marker: col.table_marker,
+            len: col.data().len(),
+            blocks,
+        }
+    }
+    pub fn decompress(&self) -> Column<M, T> {
+        let mut data = Vec::with_capacity(self.len);
+        for block in &self.blocks {
+            match block.cache.get() {
+                Some(cached) => data.extend_from_slice(cached),
+                None => data.extend(block.decode()),
+            }
+        }
+        Column {
+            table_marker: self.table_marker,
+            data,
+        }
+    }
+    pub fn len(&self) -> usize { self.len }
+    pub fn is_empty(&self) -> bool { self.len == 0 }
+    /// Decompresses the block that row `i` is in, if it hasn't been already.
+    pub fn get(&self, i: impl Check<M = M>) -> Option<&T> {
+        let i = i.to_usize();
+        let block = self.blocks.get(i / Self::BLOCK)?;
+        block.cached().get(i % Self::BLOCK)
+    }
+    /// How many blocks are currently decompressed.
+    pub fn cached_blocks(&self) -> usize {
+        self.blocks.iter().filter(|block| block.cache.get().is_some()).count()
+    }
+    /// Drops every decompressed block.
+    pub fn evict(&mut self) {
+        for block in &mut self.blocks {
+            block.cache.take();
+        }
+    }
+    /// How many bytes the runs and the cached blocks take up, not counting anything the values
+    /// themselves own.
+    pub fn memory_bytes(&self) -> usize {
+        self.blocks.iter().map(|block| {
+            block.runs.capacity() * mem::size_of::<(usize, T)>()
+                + block.cache.get().map_or(0, |cached| cached.capacity() * mem::size_of::<T>())
+        }).sum()
+    }
+}
+#[cfg(feature = "compressed_column")]
+impl<M: TableMarker, T: AnyDebug + Clone, I: Check<M = M>> Index<I> for CompressedColumn<M, T> {
+    type Output = T;
+    fn index(&self, i: I) -> &T {
+        let id = Id::<M>(i.to_raw());
+        self.get(id).unwrap_or_else(|| panic!("{}: {:?} is out of bounds", type_name::<Self>(), id))
+    }
+}
+#[cfg(feature = "compressed_column")]
+unsafe impl<'a, M: TableMarker, T: AnyDebug> Extract for &'a CompressedColumn<M, T> {
+    fn each_resource(f: &mut dyn FnMut(Ty, Access)) {
+        f(Ty::of::<CompressedColumn<M, T>>(), Access::Read)
+    }
+    type Owned = Self;
+    unsafe fn extract(_universe: &Universe, rez: &mut Rez) -> Self::Owned {
+        rez.take_ref_downcast()
+    }
+    unsafe fn convert(_universe: &Universe, owned: *mut Self::Owned) -> Self {
+        *owned
+    }
+    type Cleanup = ();
+}
@@ -26,6 +26,13 @@ impl<E: 'static + Send + Sync> Tracker<E> {
         }
     }
 }
+/// See `Universe::with_event_batching`.
+pub(crate) struct EventBatch {
+    pub(crate) thread: std::thread::ThreadId,
+    /// The tables with events waiting, and how to send them.
+    pub(crate) pending: Vec<(Ty, fn(&Universe))>,
+}
+
 impl Universe {
     pub fn submit_event<E: AnyDebug + Send + Sync>(&self, e: &mut E) {
         let ty = &Ty::of::<Tracker<E>>();
@@ -40,6 +47,8 @@ impl Universe {
         });
     }
     fn submit_event0(&self, ty: &Ty, then: &mut dyn FnMut(&mut dyn AnyDebug)) {
+        // A handler that re-submits its own event would otherwise fail with a vaguer "multi-locked".
+        assert_not_held(&format_args!("submit_event({:?})", ty), &[(*ty, Access::Write)]);
         let event = unsafe {
             let mut objects = self.objects.lock().unwrap();
             if let Some(locked) = objects.get_mut(ty) {
@@ -54,7 +63,9 @@ impl Universe {
                 return;
             }
         };
+        let held = push_held(&[(*ty, Access::Write)]);
         let _release = crate::util::Defer(|| {
+            pop_held(held);
             let mut objects = self.objects.lock().unwrap();
             objects
                 .get_mut(ty)
@@ -66,7 +77,16 @@ impl Universe {
     pub fn is_tracked<E: 'static + Send + Sync>(&self) -> bool {
         self.has_ty(Ty::of::<Tracker<E>>())
     }
-    /// `owner` should be `Ty::of::<LocalTableMarker>()`.
+    /// Add a handler for `E`. Handlers are run in the order they were added.
+    ///
+    /// # Re-entrancy
+    /// Handlers run inside `submit_event`, frequently from the cleanup of the kernel that caused
+    /// the event. By then that kernel has released its locks, so a handler may `eval` kernels of
+    /// its own (this is what `add_tracker_with_ref_arg` does). What it must not do is ask for
+    /// something that is still held further up the stack: the `Tracker<E>` itself (ie, submitting
+    /// `E` again), or anything held by an enclosing kernel that took a `UniverseRef`. Reads of a
+    /// read-held resource are allowed. Anything else panics with "would deadlock" rather than
+    /// hanging.
     pub fn add_tracker<E: 'static + Send + Sync, F: FnMut(&Universe, &mut E) + 'static + Send + Sync>(&self, f: F) {
         self.add_tracker_box(Box::new(f))
     }
@@ -89,6 +109,53 @@ impl Universe {
         }
         obj.release(Access::Write);
     }
+    /// Run `f`, holding back the `LOGICAL` `Push` events of every table until it's done; then
+    /// each table sends a single `Push` for all of the rows added meanwhile. Rows that were
+    /// pushed & then removed within the batch aren't mentioned at all.
+    ///
+    /// `MEMORY` events are still sent right away, so indices and columns keep up with the rows.
+    /// Deletes aren't held back either: the removed rows' ids may be recycled within the batch,
+    /// and `LOGICAL` `Delete` trackers need to see the rows that were actually removed.
+    /// Only kernels run by this thread are batched; there can only be one batch at a time, so if
+    /// another thread is already batching, `f` just runs normally. Until the batch is over,
+    /// foreign keys from the new rows aren't followed, so removing what they point at won't
+    /// remove them. Loading and `#[dense]` tables aren't batched.
+    pub fn with_event_batching<R>(&self, f: impl FnOnce() -> R) -> R {
+        {
+            let mut batch = self.batch.lock().unwrap();
+            if batch.is_some() {
+                drop(batch);
+                return f();
+            }
+            *batch = Some(EventBatch {
+                thread: std::thread::current().id(),
+                pending: vec![],
+            });
+        }
+        // If `f` panics, whatever is left over gets sent by the tables' next flushes.
+        let _reset = Defer(|| {
+            self.batch.lock().unwrap_or_else(std::sync::PoisonError::into_inner).take();
+        });
+        let ret = f();
+        let pending = self.batch.lock().unwrap().take().map(|b| b.pending).unwrap_or_default();
+        for (_ty, send) in pending {
+            send(self);
+        }
+        ret
+    }
+    /// Arranges for `send` to be called when the batch is over. Returns `false` if this thread
+    /// isn't batching.
+    pub(crate) fn defer_to_batch(&self, ty: Ty, send: fn(&Universe)) -> bool {
+        match &mut *self.batch.lock().unwrap() {
+            Some(batch) if batch.thread == std::thread::current().id() => {
+                if !batch.pending.iter().any(|&(t, _)| t == ty) {
+                    batch.pending.push((ty, send));
+                }
+                true
+            },
+            _ => false,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -228,6 +295,34 @@ mod test_tracking {
             assert_eq!(count, 6 - 2);
         });
     }
+
+    #[test]
+    fn reverse_lookup() {
+        use crate::linkage::ForeignIndex;
+        let universe = &mut Universe::new();
+        ships::Marker::register(universe);
+        sailors::Marker::register(universe);
+        let (titanic, lusitania) = universe.eval(|mut ships: ships::Write, mut sailors: sailors::Write| {
+            let titanic = ships.push(ships::Row { name: "RMS Titanic", weight: 10 });
+            let lusitania = ships.push(ships::Row { name: "RMS Lusitania", weight: 30 });
+            sailors.push(sailors::Row { ship: titanic, name: "Alice" });
+            sailors.push(sailors::Row { ship: lusitania, name: "Bob" });
+            sailors.push(sailors::Row { ship: titanic, name: "Charles" });
+            (titanic, lusitania)
+        });
+        universe.eval(|crew: ForeignIndex<sailors::Marker, ships::Marker>, names: sailors::read::name| {
+            let on_titanic: Vec<Name> = crew.children(titanic).map(|id| names[id]).collect();
+            assert_eq!(on_titanic, vec!["Alice", "Charles"]);
+            assert_eq!(crew.children(lusitania).count(), 1);
+        });
+        universe.eval(|mut ships: ships::Write| {
+            ships.remove(lusitania);
+        });
+        universe.eval(|crew: ForeignIndex<sailors::Marker, ships::Marker>| {
+            assert!(!crew.has_children(lusitania));
+            assert!(crew.has_children(titanic));
+        });
+    }
 }
 
 /// Represents different "lifetimes of data".
@@ -262,6 +357,9 @@ pub struct Push<M: TableMarker, Lifestage> {
     pub lifestage: Unsafe<Lifestage>,
     pub ids: RunList<M>,
 }
+/// Values in a column were edited. The old values don't need to be copied into the event; the
+/// column isn't written to until the event has been handled, so `old` and `iter_changes` read
+/// them from it.
 #[derive(Debug)]
 pub struct Edit<M: TableMarker, T: AnyDebug> {
     pub(crate) col: *const Column<M, T>,
@@ -273,9 +371,28 @@ pub struct Edit<M: TableMarker, T: AnyDebug> {
 unsafe impl<M: TableMarker, T: AnyDebug> Send for Edit<M, T> {}
 unsafe impl<M: TableMarker, T: AnyDebug> Sync for Edit<M, T> {}
 impl<M: TableMarker, T: AnyDebug> Edit<M, T> {
+    /// The column, as it was before the edit. The new values haven't been written yet.
     pub fn col(&self) -> &Column<M, T> {
         unsafe { &*self.col }
     }
+    /// The value that `id` had before this edit.
+    pub fn old(&self, id: Id<M>) -> &T {
+        &self.col().data[id.to_usize()]
+    }
+    /// Iterate over `(id, old, new)` for each edited row.
+    pub fn iter_changes(&self) -> impl Iterator<Item=(Id<M>, &T, &T)> + '_ {
+        self.new
+            .iter()
+            .map(move |(id, new)| (*id, self.old(*id), new))
+    }
+    /// Like `iter_changes`, but skips rows that were accessed mutably without their value
+    /// actually changing. Useful for mirroring the column somewhere else.
+    pub fn iter_changed(&self) -> impl Iterator<Item=(Id<M>, &T, &T)> + '_
+    where
+        T: PartialEq,
+    {
+        self.iter_changes().filter(|(_, old, new)| old != new)
+    }
 }
 
 #[derive(Debug)]
@@ -284,13 +401,23 @@ pub struct Delete<M: TableMarker, Lifestage> {
     pub ids: RunList<M>,
 }
 
-#[cfg(feature = "move_event")]
+/// Every row of a table was removed at once, by `Write::clear_fast`. This is sent *instead of*
+/// the `Delete` events, so anything tracking `Delete<M, lifestage::MEMORY>` should also track
+/// this. (If `Delete<M, LOGICAL>` or `Delete<M, LOAD>` are tracked, `clear_fast` sends `Delete`s
+/// like `clear` does.)
+///
+/// `Write::reset` also sends this after its `Delete`s, so that the columns get emptied; then
+/// `ids` is empty.
+#[derive(Debug)]
+pub struct Cleared<M: TableMarker> {
+    /// The ids that were removed, if they weren't already announced by `Delete`s.
+    pub ids: RunList<M>,
+}
+
+/// Rows were moved to new ids, by a `#[dense]` table's compaction, `Write::swap_remove`, or
+/// `Write::move_row`. Each `new` id was free beforehand.
 #[derive(Debug)]
 pub struct Move<M: TableMarker> {
     /// (old, new)
     pub ids: Vec<(Id<M>, Id<M>)>,
 }
-/// Requires `move_event` feature.
-#[cfg(not(feature = "move_event"))]
-#[derive(Debug)]
-pub enum Move {}
@@ -42,7 +42,6 @@ pub unsafe trait Extract: Sized {
     /// Default is `()`, which does nothing.
     type Cleanup: Cleaner<Self>;
 }
-// FIXME: It'd be nice to have impls of Extract for tuples; up to, say, 5.
 
 pub unsafe trait Cleaner<E: Extract> {
     fn pre_cleanup(owned: E::Owned, universe: &Universe) -> Self;
@@ -53,6 +52,63 @@ unsafe impl<E: Extract> Cleaner<E> for () {
     fn post_cleanup(self, _universe: &Universe) {}
 }
 
+/// The `Cleanup` of a tuple.
+#[doc(hidden)]
+pub struct TupleCleanup<C>(C);
+
+// Tuples let a kernel take more arguments than `impl_kernel!` provides, without making it any
+// bigger. Kept short for the sake of compile times; they nest.
+macro_rules! impl_tuple_extract {
+    ($($A:ident),*) => {
+        #[allow(non_snake_case)]
+        unsafe impl<$($A: Extract,)*> Extract for ($($A,)*) {
+            fn each_resource(f: &mut dyn FnMut(Ty, Access)) {
+                $($A::each_resource(f);)*
+            }
+            type Owned = ($($A::Owned,)*);
+            unsafe fn extract(universe: &Universe, rez: &mut Rez) -> Self::Owned {
+                // Tuple expressions are evaluated in order, as `each_resource` listed them.
+                ($($A::extract(universe, rez),)*)
+            }
+            unsafe fn convert(universe: &Universe, owned: *mut Self::Owned) -> Self {
+                let ($($A,)*) = &mut *owned;
+                ($($A::convert(universe, $A),)*)
+            }
+            type Cleanup = TupleCleanup<($($A::Cleanup,)*)>;
+        }
+        #[allow(non_snake_case)]
+        unsafe impl<$($A: Extract,)*> Cleaner<($($A,)*)> for TupleCleanup<($($A::Cleanup,)*)> {
+            fn pre_cleanup(owned: ($($A::Owned,)*), universe: &Universe) -> Self {
+                let ($($A,)*) = owned;
+                TupleCleanup(($(<$A::Cleanup as Cleaner<$A>>::pre_cleanup($A, universe),)*))
+            }
+            fn post_cleanup(self, universe: &Universe) {
+                let ($($A,)*) = self.0;
+                $(<$A::Cleanup as Cleaner<$A>>::post_cleanup($A, universe);)*
+            }
+        }
+        impl_tuple_extract! { @ $($A),* }
+    };
+    (@ $_:ident) => {};
+    (@ $_:ident $(, $A:ident)*) => {
+        impl_tuple_extract! { $($A),* }
+    };
+}
+impl_tuple_extract! { A4, A3, A2, A1, A0 }
+
+/// Extracts nothing. `decl_table!` uses this to give a lifetime to contexts that would otherwise
+/// be empty, as with a table that has no columns.
+#[doc(hidden)]
+pub struct NoResources<'a>(PhantomData<&'a ()>);
+unsafe impl<'a> Extract for NoResources<'a> {
+    fn each_resource(_f: &mut dyn FnMut(Ty, Access)) {}
+    type Owned = ();
+    unsafe fn extract(_universe: &Universe, _rez: &mut Rez) -> Self::Owned {}
+    unsafe fn convert(_universe: &Universe, _owned: *mut Self::Owned) -> Self {
+        NoResources(PhantomData)
+    }
+    type Cleanup = ();
+}
 
 /// Helper trait.
 pub unsafe trait ExtractOwned {
@@ -115,8 +115,34 @@ impl<M: TableMarker> Id<M> {
     pub fn zero() -> Self { Id(M::RawId::ZERO) }
     #[inline]
     pub fn last() -> Self { Id(M::RawId::LAST) }
+    /// Reinterpret this as an id on table `B`. This is safe to call: the `unsafe impl` of
+    /// [`SameIdSpace`] is what vouches for it.
+    #[inline]
+    pub fn cast_marker<B>(self) -> Id<B>
+    where
+        M: SameIdSpace<B>,
+        B: TableMarker<RawId = M::RawId>,
+    {
+        Id(self.0)
+    }
 }
 
+/// Asserts that tables `Self` and `B` share ids: a row with id `i` in one always corresponds to
+/// the row with id `i` in the other, eg because they're pushed to & removed from in lockstep.
+/// This permits `Id::cast_marker`.
+///
+/// # Safety
+/// Implementors must ensure that, at all times, an id is live in `Self` exactly when it's live in
+/// `B`, and that both refer to the same logical row. Code is allowed to rely on this, eg by
+/// indexing `B` with a cast id that was only checked against `Self`.
+///
+/// ```compile_fail
+/// v9::decl_table! { pub struct a { pub x: u32, } }
+/// v9::decl_table! { pub struct b { pub y: u64, } }
+/// fn cast(i: a::Id) -> b::Id { i.cast_marker() }
+/// ```
+pub unsafe trait SameIdSpace<B: TableMarker>: TableMarker {}
+
 /// An `Id` that is known to be in-bounds on the given table.
 /// You should check the Id if you'll be doing a lot of indexing.
 // Hmm, unsound if the columns have inconsistent lengths.
@@ -146,6 +172,21 @@ impl<'a, M: TableMarker> fmt::Debug for CheckedId<'a, M> {
         write!(f, "{}[{:?}]", M::NAME, self.id.0)
     }
 }
+impl<'a, M: TableMarker> CheckedId<'a, M> {
+    /// Step forward by `n`, staying checked. Gives `None` if that would go past the end of
+    /// `ids`. (The result may be a hole; it's merely in bounds.)
+    pub fn checked_add<'b>(self, n: usize, ids: &'b IdList<M>) -> Option<CheckedId<'b, M>> {
+        let i = self.id.to_usize().checked_add(n)?;
+        if i >= ids.outer_capacity() { return None; }
+        Some(CheckedId { table: PhantomData, id: Id::from_usize(i) })
+    }
+    /// Step backward by `n`, staying checked. Gives `None` if that would go below zero.
+    pub fn checked_sub<'b>(self, n: usize, ids: &'b IdList<M>) -> Option<CheckedId<'b, M>> {
+        let i = self.id.to_usize().checked_sub(n)?;
+        if i >= ids.outer_capacity() { return None; }
+        Some(CheckedId { table: PhantomData, id: Id::from_usize(i) })
+    }
+}
 pub unsafe trait Check: Copy + Ord + fmt::Debug {
     type M: TableMarker;
     unsafe fn check_from_capacity<'a>(
@@ -240,6 +281,9 @@ impl<M: TableMarker> From<usize> for Id<M> {
 }
 
 /// This is an exclusive range, just like `std::ops::Range`.
+///
+/// With the `serde` feature, only `start` and `end` are saved, so a range can be stored in a
+/// column (like a `'static` `Range` of another table) and loaded back by `deserialize_table`.
 #[derive(Clone, Copy, Ord, PartialOrd, Eq, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "serde", serde(bound = "I: serde::Serialize + serde::de::DeserializeOwned"))]
@@ -327,6 +371,30 @@ impl<'a, I: Check> IdRange<'a, I> {
             None
         }
     }
+    /// The ids in both ranges, or `None` if there aren't any.
+    pub fn intersect(&self, other: &Self) -> Option<Self> {
+        let start = self.start.max(other.start);
+        let end = self.end.min(other.end);
+        if start < end {
+            Some(IdRange { _a: PhantomData, start, end })
+        } else {
+            None
+        }
+    }
+    /// The ids in either range, if that is itself a range; ie, if they overlap or are adjacent.
+    /// An empty range joins with anything.
+    pub fn union_contiguous(&self, other: &Self) -> Option<Self> {
+        if self.is_empty() { return Some(*other); }
+        if other.is_empty() { return Some(*self); }
+        if self.start.max(other.start) > self.end.min(other.end) {
+            return None;
+        }
+        Some(IdRange {
+            _a: PhantomData,
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        })
+    }
 }
 impl<M: TableMarker> IdRange<'static, Id<M>> {
     pub fn new(start: Id<M>, end: Id<M>) -> Self {
@@ -364,6 +432,26 @@ impl<M: TableMarker> IdRange<'static, Id<M>> {
             _a: PhantomData,
         }
     }
+    /// Split the range into consecutive subranges of `size` ids; the last may be shorter. Panics
+    /// if `size` is 0.
+    pub fn chunks(self, size: usize) -> impl Iterator<Item=Self> {
+        assert!(size > 0, "chunk size must be positive");
+        let end = self.end.to_usize();
+        (self.start.to_usize()..end).step_by(size).map(move |start| {
+            IdRange::new(Id::from_usize(start), Id::from_usize((start + size).min(end)))
+        })
+    }
+    /// Pair each id with a datum; eg, the result of `push_contiguous` with values for another
+    /// column. Panics if `data` isn't the same length as the range.
+    pub fn zip<T>(self, data: impl IntoIterator<Item=T>) -> impl Iterator<Item=(Id<M>, T)> {
+        let mut ids = self.iter();
+        let mut data = data.into_iter();
+        std::iter::from_fn(move || match (ids.next(), data.next()) {
+            (Some(id), Some(t)) => Some((id, t)),
+            (None, None) => None,
+            _ => panic!("{}", crate::util::die::ZIP_LEN_MISMATCH),
+        })
+    }
 }
 #[derive(Clone)]
 pub struct IdRangeIter<'a, I: Check> {
@@ -416,23 +504,119 @@ impl<M: TableMarker> From<RangeInclusive<Id<M>>> for UncheckedIdRange<M> {
     }
 }
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Clone)]
 #[repr(C)]
 pub struct IdList<M: TableMarker> {
     inner: runlist::IdList<M::RawId>,
     event_commitment: EventCommitment,
     load_events: bool,
+    dense: bool,
+    /// `(from, to)` pairs from `request_move`.
+    moves_requested: Vec<(Id<M>, Id<M>)>,
+    /// Holes from `request_swap`.
+    swaps_requested: Vec<Id<M>>,
+    cleared: Option<RunList<M>>,
+    just_cleared: Option<RunList<M>>,
+    resetting: bool,
+    /// Pushed ids whose `LOGICAL` events are being held back by `Universe::with_event_batching`.
+    batched: Option<RunList<M>>,
+}
+/// Lists bigger than this are only summarized by `Debug`, unless it's `{:#?}`.
+const DEBUG_SUMMARY_THRESHOLD: usize = 64;
+impl<M: TableMarker> fmt::Debug for IdList<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.len() > DEBUG_SUMMARY_THRESHOLD && !f.alternate() {
+            return write!(f, "IdList {{ {} ids, capacity {} }}", self.len(), self.outer_capacity());
+        }
+        f.debug_struct("IdList")
+            .field("inner", &self.inner)
+            .field("event_commitment", &self.event_commitment)
+            .field("load_events", &self.load_events)
+            .field("dense", &self.dense)
+            .field("moves_requested", &self.moves_requested)
+            .field("swaps_requested", &self.swaps_requested)
+            .field("cleared", &self.cleared)
+            .field("resetting", &self.resetting)
+            .field("batched", &self.batched)
+            .finish()
+    }
 }
 impl<M: TableMarker> IdList<M> {
     pub fn validate(&self) { self.inner.assert().unwrap(); }
     #[inline] pub fn set_load(&mut self) { self.load_events = true; }
+    /// Used by `#[dense]` tables. Deleted ids are filled in by the highest ids after each flush.
+    #[doc(hidden)]
+    #[inline] pub fn set_dense(&mut self) { self.dense = true; }
+    #[inline] pub fn is_dense(&self) -> bool { self.dense }
+    /// Used by `Write::move_row`. The row at `from` is moved to `to` by `take_moves`.
+    #[doc(hidden)]
+    #[inline] pub fn request_move(&mut self, from: Id<M>, to: Id<M>) { self.moves_requested.push((from, to)); }
+    /// Used by `Write::swap_remove`, after deleting `hole`. `take_moves` moves the row with the
+    /// highest id into it.
+    #[doc(hidden)]
+    #[inline] pub fn request_swap(&mut self, hole: Id<M>) { self.swaps_requested.push(hole); }
+    /// Used by `Write::clear_fast`. Every id is deleted. If the deletions aren't tracked too
+    /// closely, the flush forgets the whole list and a `Cleared` event is sent instead.
+    #[doc(hidden)]
+    pub fn clear_fast(&mut self) {
+        let live = self.live_ids();
+        if live.is_empty() { return; }
+        self.delete_extend_ranges(live.iter_runs_inclusive());
+        match &mut self.cleared {
+            Some(cleared) => cleared.merge(&live),
+            None => self.cleared = Some(live),
+        }
+    }
+    /// Used by `Write::reset`. Every id is deleted, as usual; then once the deletions have been
+    /// flushed, the id space starts over, and a `Cleared` event with no ids (as they've already
+    /// been announced) is sent so that the columns are emptied. If anything was pushed after the
+    /// reset, the id space is left alone.
+    #[doc(hidden)]
+    pub fn reset(&mut self) {
+        let live = self.live_ids();
+        self.delete_extend_ranges(live.iter_runs_inclusive());
+        self.resetting = true;
+    }
+    /// The ids removed by `clear_fast` or `reset`, if the last flush started over.
+    #[doc(hidden)]
+    pub fn take_cleared(&mut self) -> Option<RunList<M>> { self.just_cleared.take() }
     #[inline] pub fn len(&self) -> usize { self.inner.len() }
     #[inline] pub fn is_empty(&self) -> bool { self.inner.is_empty() }
     #[inline] pub fn outer_capacity(&self) -> usize { M::RawId::to_usize(self.inner.outer_capacity()) }
     #[inline] pub fn exists(&self, id: Id<M>) -> bool { self.inner.exists(id.0) }
+    /// How many freed ids are waiting to be recycled.
+    pub fn free_len(&self) -> usize { self.outer_capacity() - self.len() }
+    /// How many contiguous runs the freed ids make up; a measure of fragmentation.
+    pub fn free_runs(&self) -> usize { self.free_ids().get_data().len() }
+    /// Whether the `LOGICAL` `Push` events of this flush should wait for the end of the batch. If
+    /// they shouldn't, anything left over from a batch that was interrupted by a panic is sent
+    /// now, so that it still comes before this flush's events.
+    fn defer_logical(&mut self, universe: &Universe) -> bool {
+        // Dense tables compact after every flush, which would move rows out from under the
+        // batched ids.
+        if !self.dense && universe.defer_to_batch(Ty::of::<IdList<M>>(), send_batched::<M>) {
+            return true;
+        }
+        submit_batched(universe, self.batched.take());
+        false
+    }
     pub fn flush(&mut self, universe: &Universe) {
-        if let (EventCommitment::None, false, false) = (self.event_commitment, self.inner.has_pushing(), self.inner.has_deleting()) { return; }
+        if let (EventCommitment::None, false, false, false) = (self.event_commitment, self.inner.has_pushing(), self.inner.has_deleting(), self.resetting) { return; }
         self.event_commitment = EventCommitment::None;
+        if let Some(cleared) = self.cleared.take() {
+            let fast = !self.resetting
+                && !self.inner.has_pushing()
+                && !universe.is_tracked::<Delete<M, lifestage::LOGICAL>>()
+                && !universe.is_tracked::<Delete<M, lifestage::LOAD>>();
+            if fast {
+                // Everything is being deleted, so there's nothing to keep.
+                self.inner = Default::default();
+                self.load_events = false;
+                self.batched = None;
+                self.just_cleared = Some(cleared);
+                return;
+            }
+        }
         let load = self.load_events;
         let logi = !load;
         let (track_push, track_delete) = (
@@ -443,6 +627,8 @@ impl<M: TableMarker> IdList<M> {
                     || (load && universe.is_tracked::<Push<M, lifestage::LOAD>>())
             }, {
                 false
+                    // The batch needs to hear about its rows being deleted.
+                    || self.batched.is_some()
                     || universe.is_tracked::<Delete<M, lifestage::MEMORY>>()
                     || (logi && universe.is_tracked::<Delete<M, lifestage::LOGICAL>>())
                     || (load && universe.is_tracked::<Delete<M, lifestage::LOAD>>())
@@ -460,6 +646,11 @@ impl<M: TableMarker> IdList<M> {
                     self.load_events = false;
                     let mut event = Push { lifestage: unsafe { Unsafe::new(lifestage::LOAD) }, ids };
                     universe.submit_event(&mut event);
+                } else if self.defer_logical(universe) {
+                    match &mut self.batched {
+                        Some(batched) => batched.merge(&ids),
+                        None => self.batched = Some(ids),
+                    }
                 } else {
                     let mut event = Push { lifestage: unsafe { Unsafe::new(lifestage::LOGICAL) }, ids };
                     universe.submit_event(&mut event);
@@ -473,14 +664,38 @@ impl<M: TableMarker> IdList<M> {
                     universe.submit_event(&mut event);
                     event.ids
                 } else {
-                    let mut event = Delete { lifestage: unsafe { Unsafe::new(lifestage::LOGICAL) }, ids };
-                    universe.submit_event(&mut event);
-                    event.ids
+                    // Deletes aren't held back: the freed ids may be recycled before the batch is
+                    // over, and then the `LOGICAL` trackers would be looking at the wrong rows.
+                    // This only sends whatever an interrupted batch left over.
+                    self.defer_logical(universe);
+                    let announced = match &mut self.batched {
+                        // Nobody has heard of these yet, so they needn't hear that they're gone.
+                        Some(batched) => {
+                            let announced = ids.difference(batched);
+                            *batched = batched.difference(&ids);
+                            announced
+                        },
+                        None => ids.clone(),
+                    };
+                    if !announced.is_empty() {
+                        let mut event = Delete { lifestage: unsafe { Unsafe::new(lifestage::LOGICAL) }, ids: announced };
+                        universe.submit_event(&mut event);
+                    }
+                    ids
                 };
                 let mut event = Delete { lifestage: unsafe { Unsafe::new(lifestage::MEMORY) }, ids };
                 universe.submit_event(&mut event);
             },
         }
+        if mem::replace(&mut self.resetting, false) {
+            if !self.inner.has_pushing() && !self.inner.has_deleting() && self.inner.is_empty() {
+                // The deletions have all been seen, so the id space can start over.
+                self.inner = Default::default();
+                self.load_events = false;
+                self.batched = None;
+                self.just_cleared = Some(RunList::new());
+            }
+        }
     }
     #[inline]
     pub fn iter(&self) -> CheckedIter<M> {
@@ -488,6 +703,52 @@ impl<M: TableMarker> IdList<M> {
             inner: self.inner.iter_singles(),
         }
     }
+    /// Like `iter`, but in strictly ascending order, for things like reproducible serialization.
+    /// This walks the runs of `live_ids`.
+    pub fn sorted_iter(&self) -> impl Iterator<Item=CheckedId<M>> + '_ {
+        let runs = self.live_ids().get_data().to_vec();
+        runs.into_iter()
+            .flat_map(|[start, end]| M::RawId::to_usize(start)..=M::RawId::to_usize(end))
+            .map(|i| CheckedId { table: PhantomData, id: Id::from_usize(i) })
+    }
+    /// The freed ids that are waiting to be recycled. runlist doesn't expose its free list, but
+    /// it's exactly what `recycle_ids_sparse` hands out when asked for all of it, so we ask a
+    /// copy. This is `O(free runs)`.
+    pub fn free_ids(&self) -> RunList<M> {
+        let n = self.free_len();
+        if n == 0 { return RunList::new(); }
+        let mut inner = self.inner.clone();
+        let recycle = inner.recycle_ids_sparse(M::RawId::from_usize(n));
+        RunList { inner: recycle.replace }
+    }
+    /// The live ids, as runs; the gaps between the runs of `free_ids`.
+    pub fn live_ids(&self) -> RunList<M> {
+        let mut live = RunList::new();
+        let mut next = 0;
+        for run in self.free_ids().iter_runs_inclusive() {
+            let (start, end) = (run.start().to_usize(), run.end().to_usize());
+            if start > next {
+                live.push_run(Id::from_usize(next)..=Id::from_usize(start - 1));
+            }
+            next = end + 1;
+        }
+        let cap = self.outer_capacity();
+        if cap > next {
+            live.push_run(Id::from_usize(next)..=Id::from_usize(cap - 1));
+        }
+        live
+    }
+    /// Iterate over the live ids that are also in `sel`, in ascending order. The freed ids are
+    /// taken out of `sel` run by run, so this is `O(runs)` rather than `O(len)`.
+    pub fn iter_selected<'a>(&'a self, sel: &'a RunList<M>) -> SelectedIter<'a, M> {
+        let selected = sel.difference(&self.free_ids());
+        SelectedIter {
+            _ids: PhantomData,
+            runs: selected.get_data().to_vec().into_iter(),
+            run: None,
+            end: self.outer_capacity(),
+        }
+    }
     #[inline]
     pub fn delete(&mut self, id: Id<M>) {
         self.event_commitment.put(EventCommitment::Delete { event: true });
@@ -530,6 +791,9 @@ impl<M: TableMarker> IdList<M> {
     /// # Safety
     /// This function is unsafe because it does not push anything to the tables's column vectors.
     pub unsafe fn recycle_id(&mut self, event: bool) -> Result<Id<M>, Id<M>> {
+        if self.next_recycle_id() == Id::last() {
+            exhausted::<M>();
+        }
         self.event_commitment.pushing(event);
         match self.inner.recycle_id() {
             Ok(id) => Ok(Id(id)),
@@ -540,6 +804,7 @@ impl<M: TableMarker> IdList<M> {
     /// # Safety
     /// This function is unsafe because it does not push anything to the tables's column vectors.
     pub unsafe fn recycle_ids(&mut self, n: usize, event: bool) -> Recycle<M> {
+        self.assert_room(n);
         self.event_commitment.pushing(event);
         let n = M::RawId::from_usize(n);
         let recycle = self.inner.recycle_ids_sparse(n);
@@ -557,6 +822,7 @@ impl<M: TableMarker> IdList<M> {
     /// # Safety
     /// This function is unsafe because it does not push anything to the tables's column vectors.
     pub unsafe fn recycle_ids_contiguous(&mut self, n: usize, event: bool) -> Recycle<M> {
+        self.assert_room(n);
         self.event_commitment.pushing(event);
         let n = M::RawId::from_usize(n);
         let recycle = self.inner.recycle_ids_contiguous(n);
@@ -570,6 +836,204 @@ impl<M: TableMarker> IdList<M> {
             },
         }
     }
+    /// Replace the list with one that has exactly `live` in use and `free` available for
+    /// recycling, as when loading a save. No events are sent.
+    ///
+    /// `live` and `free` must be disjoint and together cover `0..capacity`. runlist keeps its free
+    /// ids in a `RunList`, so the order they're recycled in depends only on which ones are free;
+    /// the loaded list recycles them just as the saved one would have. This is checked.
+    pub fn load_state(&mut self, live: &RunList<M>, free: &RunList<M>, capacity: usize) -> Result<(), String> {
+        if capacity >= M::RawId::LAST.to_usize() {
+            return Err(format!("capacity {} is too large for {}", capacity, M::NAME));
+        }
+        if live.len() + free.len() != capacity {
+            return Err(format!(
+                "live ({}) and free ({}) ids don't add up to the capacity ({})",
+                live.len(), free.len(), capacity,
+            ));
+        }
+        if live.difference(free).len() != live.len() {
+            return Err("live and free ids overlap".to_string());
+        }
+        for run in live.iter_runs_inclusive().chain(free.iter_runs_inclusive()) {
+            if run.end().to_usize() >= capacity {
+                return Err(format!("id {:?} is beyond the capacity ({})", run.end(), capacity));
+            }
+        }
+        let mut inner = runlist::IdList::default();
+        if capacity != 0 {
+            let _ = inner.recycle_ids_contiguous(M::RawId::from_usize(capacity));
+            let _ = inner.flush(false, false);
+            inner.delete_ids(free.iter_runs_inclusive().map(|r| r.start().to_raw()..=r.end().to_raw()));
+            let _ = inner.flush(false, false);
+        }
+        inner.assert()?;
+        let mut loaded = IdList::<M>::default();
+        loaded.inner = inner;
+        if loaded.free_ids() != *free {
+            return Err(format!("the free ids of {} couldn't be reproduced", M::NAME));
+        }
+        self.inner = loaded.inner;
+        self.event_commitment = EventCommitment::None;
+        Ok(())
+    }
+    /// Like `load_state`, but every id below `capacity` that isn't in `live` is free. The list
+    /// must be empty.
+    pub fn load_live(&mut self, live: &RunList<M>, capacity: usize) -> Result<(), String> {
+        if !self.is_empty() || self.outer_capacity() != 0 {
+            return Err(format!("{} must be empty to load into it", M::NAME));
+        }
+        if capacity >= M::RawId::LAST.to_usize() {
+            return Err(format!("capacity {} is too large for {}", capacity, M::NAME));
+        }
+        let mut free = RunList::new();
+        if capacity != 0 {
+            free.push_run(Id::from_usize(0)..=Id::from_usize(capacity - 1));
+        }
+        let free = free.difference(live);
+        self.load_state(live, &free, capacity)
+    }
+    /// Forget the free ids above the highest live id, so that the columns may be truncated to the
+    /// returned capacity. Does nothing if there are pending pushes or deletes.
+    pub fn trim(&mut self) -> usize {
+        if self.inner.has_pushing() || self.inner.has_deleting() {
+            return self.outer_capacity();
+        }
+        // `iter` isn't necessarily ascending.
+        let capacity = self.iter().map(|id| id.to_usize()).max().map_or(0, |max| max + 1);
+        if capacity == self.outer_capacity() {
+            return capacity;
+        }
+        let mut live = RunList::new();
+        live.extend(self.sorted_iter().map(|id| id.uncheck()));
+        let mut free = RunList::new();
+        if capacity != 0 {
+            free.push_run(Id::from_usize(0)..=Id::from_usize(capacity - 1));
+        }
+        let free = free.difference(&live);
+        self.load_state(&live, &free, capacity).expect("IdList::trim");
+        capacity
+    }
+    /// Get ready for `additional` more ids to be pushed. This panics now, rather than partway
+    /// through the pushes, if they wouldn't fit in the id space. runlist has no hook for
+    /// allocating ahead of time; its pending pushes are coalesced into runs, so they seldom need
+    /// much room anyway.
+    pub fn reserve(&mut self, additional: usize) {
+        self.assert_room(additional);
+    }
+    /// Panics if `n` more ids would reach `RawId::LAST`, which is reserved as invalid.
+    fn assert_room(&self, n: usize) {
+        let free = self.outer_capacity() - self.len();
+        let fresh = n.saturating_sub(free);
+        if self.outer_capacity() + fresh > M::RawId::LAST.to_usize() {
+            exhausted::<M>();
+        }
+    }
+    /// Renumber the live ids so that they are exactly `0..len()`. Returns the `(old, new)` pairs;
+    /// the caller is responsible for moving the column data to match.
+    ///
+    /// Panics if there are pending pushes or deletes.
+    ///
+    /// This walks the runs of free ids below `len()` and of live ids above it, so it's
+    /// proportional to the number of holes rather than to the number of rows.
+    pub fn compact(&mut self) -> Vec<(Id<M>, Id<M>)> {
+        assert!(
+            !self.inner.has_pushing() && !self.inner.has_deleting(),
+            "can't compact {} with pending pushes or deletes",
+            M::NAME,
+        );
+        let len = self.len();
+        // The ids of `list`'s runs that fall within `range`.
+        let clip = |list: RunList<M>, range: Range<usize>| -> Vec<usize> {
+            let mut ids = vec![];
+            for run in list.iter_runs_inclusive() {
+                let start = run.start().to_usize().max(range.start);
+                let end = (run.end().to_usize() + 1).min(range.end);
+                ids.extend(start..end);
+            }
+            ids
+        };
+        let holes = clip(self.free_ids(), 0..len);
+        let movers = clip(self.live_ids(), len..self.outer_capacity());
+        let moves: Vec<_> = movers.into_iter()
+            .zip(holes)
+            .map(|(from, to)| (Id::from_usize(from), Id::from_usize(to)))
+            .collect();
+        let mut inner = runlist::IdList::default();
+        let _ = inner.recycle_ids_contiguous(M::RawId::from_usize(len));
+        let _ = inner.flush(false, false);
+        self.inner = inner;
+        moves
+    }
+    /// Does the moves asked for by `request_move`, in order, and then fills each hole from
+    /// `request_swap` with the row that has the highest id, unless the hole has been recycled or
+    /// is above every row. Returns the `(old, new)` pairs; the caller is responsible for moving
+    /// the column data to match.
+    ///
+    /// Panics if there are pending pushes or deletes, or if a move isn't from a live id to a free
+    /// one.
+    // FIXME: This is O(outer_capacity).
+    pub fn take_moves(&mut self) -> Option<Vec<(Id<M>, Id<M>)>> {
+        if self.moves_requested.is_empty() && self.swaps_requested.is_empty() { return None; }
+        let mut moves = mem::take(&mut self.moves_requested);
+        let swaps = mem::take(&mut self.swaps_requested);
+        assert!(
+            !self.inner.has_pushing() && !self.inner.has_deleting(),
+            "can't move rows of {} with pending pushes or deletes",
+            M::NAME,
+        );
+        assert!(
+            self.batched.is_none(),
+            "can't move rows of {} while events are batched",
+            M::NAME,
+        );
+        let cap = self.outer_capacity();
+        let mut live = vec![false; cap];
+        for id in self.iter() {
+            live[id.to_usize()] = true;
+        }
+        for &(from, to) in &moves {
+            let (f, t) = (from.to_usize(), to.to_usize());
+            if f >= cap || !live[f] {
+                panic!("can't move {:?}: it doesn't exist", from);
+            }
+            if t >= cap || live[t] {
+                panic!("can't move {:?} to {:?}: it isn't free", from, to);
+            }
+            live[f] = false;
+            live[t] = true;
+        }
+        let mut top = cap;
+        for hole in swaps {
+            let h = hole.to_usize();
+            if h >= cap || live[h] { continue; }
+            while top > 0 && !live[top - 1] {
+                top -= 1;
+            }
+            if top <= h { continue; }
+            let last = top - 1;
+            live[last] = false;
+            live[h] = true;
+            moves.push((Id::from_usize(last), hole));
+        }
+        let mut holes = RunList::<M>::new();
+        holes.extend((0..cap).filter(|&i| !live[i]).map(Id::<M>::from_usize));
+        let mut inner = runlist::IdList::default();
+        let _ = inner.recycle_ids_contiguous(M::RawId::from_usize(cap));
+        let _ = inner.flush(false, false);
+        inner.delete_ids(holes.iter_runs_inclusive().map(|r| r.start().to_raw()..=r.end().to_raw()));
+        let _ = inner.flush(false, false);
+        self.inner = inner;
+        Some(moves)
+    }
+    /// Calls `compact()` if this is the list of a `#[dense]` table that has holes.
+    pub fn compact_if_dense(&mut self) -> Option<Vec<(Id<M>, Id<M>)>> {
+        if self.dense && self.outer_capacity() != self.len() {
+            Some(self.compact())
+        } else {
+            None
+        }
+    }
     pub fn check<'a, 'b>(&'a self, i: impl Check<M=M> + 'b) -> CheckedId<'a, M> {
         unsafe {
             i.check_from_capacity(
@@ -579,6 +1043,12 @@ impl<M: TableMarker> IdList<M> {
         }
     }
 }
+/// `IdList`s are equal if they have the same live ids.
+impl<M: TableMarker> PartialEq for IdList<M> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.sorted_iter().map(|i| i.uncheck()).eq(other.sorted_iter().map(|i| i.uncheck()))
+    }
+}
 impl<'a, M: TableMarker> IntoIterator for &'a IdList<M> {
     type Item = CheckedId<'a, M>;
     type IntoIter = CheckedIter<'a, M>;
@@ -654,6 +1124,17 @@ unsafe impl<'a, M: TableMarker> Extract for &'a mut IdList<M> {
     }
     type Cleanup = IdListCleanup;
 }
+/// Sends the `Push` events held back by `Universe::with_event_batching`.
+fn send_batched<M: TableMarker>(universe: &Universe) {
+    let pushed = universe.with_mut(|list: &mut IdList<M>| list.batched.take());
+    submit_batched(universe, pushed);
+}
+fn submit_batched<M: TableMarker>(universe: &Universe, pushed: Option<RunList<M>>) {
+    if let Some(ids) = pushed.filter(|ids| !ids.is_empty()) {
+        let mut event = Push { lifestage: unsafe { Unsafe::new(lifestage::LOGICAL) }, ids };
+        universe.submit_event(&mut event);
+    }
+}
 pub const TRACK_PUSH: u8 = 1;
 pub const TRACK_DELETE: u8 = 2;
 #[doc(hidden)]
@@ -672,9 +1153,24 @@ unsafe impl<'a, M: TableMarker> Cleaner<&'a mut IdList<M>> for IdListCleanup {
         // Would a "reentrant lock" help here?
         // Possibly the problem is that any arbitrary dang thing can have a dependence hanging off
         // of the event being processed. We can't even look ahead! And it could be very recursive!
-        universe.with_mut(|owned: &mut IdList<M>| {
+        let (moved, compacted, cleared) = universe.with_mut(|owned: &mut IdList<M>| {
             owned.flush(universe);
+            (owned.take_moves(), owned.compact_if_dense(), owned.take_cleared())
         });
+        if let Some(ids) = cleared {
+            // The table's own tracker empties the columns first.
+            universe.submit_event(&mut Cleared { ids });
+        }
+        // The table's own tracker moves the column data first; see `decl_table!`'s `#[dense]`,
+        // `swap_remove`, and `move_row`. The two batches are separate events, as a row could be
+        // in both.
+        for ids in moved.into_iter().chain(compacted) {
+            if ids.is_empty() { continue; }
+            if universe.has_ty(Ty::of::<MustKeepContiguous<M>>()) {
+                panic!("can't move rows of {}: an IdRange foreign key needs them to stay put", M::NAME);
+            }
+            universe.submit_event(&mut Move { ids });
+        }
     }
 }
 
@@ -748,6 +1244,29 @@ impl<'a, M: TableMarker> Iterator for CheckedIter<'a, M> {
     }
 }
 
+/// The intersection of an `IdList` and a `RunList`. See `IdList::iter_selected`.
+#[derive(Debug, Clone)]
+pub struct SelectedIter<'a, M: TableMarker> {
+    _ids: PhantomData<&'a IdList<M>>,
+    runs: std::vec::IntoIter<[M::RawId; 2]>,
+    run: Option<RangeInclusive<usize>>,
+    /// `sel` may go past the end of the list.
+    end: usize,
+}
+impl<'a, M: TableMarker> Iterator for SelectedIter<'a, M> {
+    type Item = CheckedId<'a, M>;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(i) = self.run.as_mut().and_then(|run| run.next()) {
+                if i >= self.end { return None; }
+                return Some(CheckedId { table: PhantomData, id: Id::from_usize(i) });
+            }
+            let [start, end] = self.runs.next()?;
+            self.run = Some(M::RawId::to_usize(start)..=M::RawId::to_usize(end));
+        }
+    }
+}
+
 /// Stores `Id`s with great efficiency. Runs are stored like a `RangeInclusive`. (In the case of a
 /// single run, zero allocation is needed.) Non-contiguous `Id`s have the same memory overhead as a
 /// `Vec`.
@@ -762,6 +1281,14 @@ pub struct RunList<M: TableMarker> {
 }
 impl<M: TableMarker> fmt::Debug for RunList<M> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let runs = self.get_data().len();
+        if runs > DEBUG_SUMMARY_THRESHOLD && !f.alternate() {
+            let first = self.iter_runs_inclusive().next().map(|r| r.start().to_usize());
+            let last = self.iter_runs_inclusive().last().map(|r| r.end().to_usize());
+            if let (Some(first), Some(last)) = (first, last) {
+                return write!(f, "[{} ids, {} runs, {}..={}]", self.len(), runs, first, last);
+            }
+        }
         write!(f, "[{:?}]", self.inner)
     }
 }
@@ -802,6 +1329,11 @@ impl<M: TableMarker> RunList<M> {
         Ok(RunList { inner })
     }
     pub fn validate_data(&self) -> Result<(), String> { self.inner.assert() }
+    /// Release any excess capacity left over from runs that have since been removed.
+    // runlist doesn't expose its Vec, so we copy the runs into a new, exactly-sized one.
+    pub fn shrink_to_fit(&mut self) {
+        *self = Self::from_raw_data(self.len(), self.get_data().to_vec()).expect("RunList was invalid");
+    }
     #[inline] pub fn len(&self) -> usize { self.inner.len() }
     #[inline] pub fn is_empty(&self) -> bool { self.inner.is_empty() }
     #[inline] pub fn push(&mut self, i: Id<M>) { self.inner.push(i.0); }
@@ -818,8 +1350,194 @@ impl<M: TableMarker> RunList<M> {
             self.inner.push(id.to_raw());
         }
     }
-    // FIXME: fn merge(&mut self, other: &Self);
+    /// Build a list from ids that are already sorted & deduplicated, coalescing them into runs
+    /// in a single pass. Errors if the ids go backwards or repeat.
+    pub fn from_sorted_iter(iter: impl IntoIterator<Item=Id<M>>) -> Result<Self, String> {
+        let mut data: Vec<[M::RawId; 2]> = vec![];
+        let mut len = 0;
+        let mut last: Option<Id<M>> = None;
+        for id in iter {
+            if let Some(prev) = last {
+                if id.to_usize() <= prev.to_usize() {
+                    return Err(format!("ids are not sorted & unique: {:?} came after {:?}", id, prev));
+                }
+                if id.to_usize() == prev.to_usize() + 1 {
+                    data.last_mut().unwrap()[1] = id.to_raw();
+                    last = Some(id);
+                    len += 1;
+                    continue;
+                }
+            }
+            data.push([id.to_raw(), id.to_raw()]);
+            last = Some(id);
+            len += 1;
+        }
+        Self::from_raw_data(len, data)
+    }
+    /// The ids that are in `self`, but not in `other`.
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut out = Self::new();
+        let mut holes = other
+            .iter_runs_inclusive()
+            .map(|r| (r.start().to_usize(), r.end().to_usize()))
+            .peekable();
+        for run in self.iter_runs_inclusive() {
+            let (mut start, end) = (run.start().to_usize(), run.end().to_usize());
+            while let Some(&(hole_start, hole_end)) = holes.peek() {
+                if hole_end < start {
+                    holes.next();
+                    continue;
+                }
+                if hole_start > end {
+                    break;
+                }
+                if hole_start > start {
+                    out.push_run(Id::from_usize(start)..=Id::from_usize(hole_start - 1));
+                }
+                if hole_end >= end {
+                    // The hole might cover the next run too, so keep it.
+                    start = end + 1;
+                    break;
+                }
+                start = hole_end + 1;
+                holes.next();
+            }
+            if start <= end {
+                out.push_run(Id::from_usize(start)..=Id::from_usize(end));
+            }
+        }
+        out
+    }
+    /// Returns `(added, removed)`: the ids that are only in `new`, and the ids that are only in
+    /// `self`.
+    pub fn diff(&self, new: &Self) -> (Self, Self) {
+        (new.difference(self), self.difference(new))
+    }
+    /// Iterate over the ranges within `within` that aren't in the list; the complement.
+    pub fn iter_gaps(&self, within: IdRange<'static, Id<M>>) -> impl Iterator<Item=IdRange<'static, Id<M>>> + '_ {
+        let hi = within.end.to_usize();
+        let mut at = within.start.to_usize();
+        let mut runs = self.iter_runs_inclusive();
+        let mut done = false;
+        let gap = |start: usize, end: usize| IdRange::new(Id::from_usize(start), Id::from_usize(end));
+        std::iter::from_fn(move || {
+            while !done {
+                let run = if let Some(run) = runs.next() {
+                    run
+                } else {
+                    done = true;
+                    return if at < hi { Some(gap(at, hi)) } else { None };
+                };
+                let (start, end) = (run.start().to_usize(), run.end().to_usize() + 1);
+                if end <= at { continue; }
+                let found = (at, start.min(hi));
+                at = end;
+                if at >= hi { done = true; }
+                if found.0 < found.1 {
+                    return Some(gap(found.0, found.1));
+                }
+            }
+            None
+        })
+    }
+    /// Appends the runs that were added & removed since `prev` to `out`. This is usually much
+    /// smaller than the whole list; the receiver uses `apply_delta` on its copy of `prev`.
+    ///
+    /// The format is a count of runs followed by each run as a pair of varints, the gap since the
+    /// end of the last run and the run's length; first for the added runs, then the removed.
+    pub fn encode_delta(&self, prev: &RunList<M>, out: &mut Vec<u8>) {
+        let (added, removed) = prev.diff(self);
+        for list in &[added, removed] {
+            put_varint(out, list.get_data().len());
+            let mut at = 0;
+            for run in list.iter_runs_inclusive() {
+                let (start, end) = (run.start().to_usize(), run.end().to_usize());
+                put_varint(out, start - at);
+                put_varint(out, end - start);
+                at = end + 1;
+            }
+        }
+    }
+    /// Reads a delta written by `encode_delta`, turning the `prev` list into the new list.
+    /// Returns the number of bytes read.
+    pub fn apply_delta(&mut self, bytes: &[u8]) -> Result<usize, String> {
+        let mut read = 0;
+        let mut lists = [RunList::new(), RunList::new()];
+        for list in &mut lists {
+            let runs = get_varint(bytes, &mut read)?;
+            let mut at = 0usize;
+            for _ in 0..runs {
+                let gap = get_varint(bytes, &mut read)?;
+                let len = get_varint(bytes, &mut read)?;
+                let (start, end) = match at.checked_add(gap).and_then(|start| Some((start, start.checked_add(len)?))) {
+                    Some((start, end)) if end < Id::<M>::last().to_usize() => (start, end),
+                    _ => return Err("RunList delta is out of range".into()),
+                };
+                list.push_run(Id::from_usize(start)..=Id::from_usize(end));
+                at = end + 1;
+            }
+        }
+        let [added, removed] = lists;
+        *self = self.difference(&removed);
+        self.merge(&added);
+        Ok(read)
+    }
+    /// Add all of the ids in `other`. Overlapping and adjacent runs are coalesced.
+    pub fn merge(&mut self, other: &Self) {
+        let mut out = Self::new();
+        let mut ours = self.iter_runs_inclusive().peekable();
+        let mut theirs = other.iter_runs_inclusive().peekable();
+        let mut pending: Option<(usize, usize)> = None;
+        loop {
+            let run = match (ours.peek(), theirs.peek()) {
+                (Some(a), Some(b)) => if a.start().to_usize() <= b.start().to_usize() { ours.next() } else { theirs.next() },
+                (Some(_), None) => ours.next(),
+                (None, Some(_)) => theirs.next(),
+                (None, None) => break,
+            };
+            let run = run.expect("peeked");
+            let (start, end) = (run.start().to_usize(), run.end().to_usize());
+            match &mut pending {
+                Some((_, pending_end)) if start <= *pending_end + 1 => {
+                    *pending_end = end.max(*pending_end);
+                },
+                _ => if let Some((s, e)) = pending.replace((start, end)) {
+                    out.push_run(Id::from_usize(s)..=Id::from_usize(e));
+                },
+            }
+        }
+        if let Some((s, e)) = pending {
+            out.push_run(Id::from_usize(s)..=Id::from_usize(e));
+        }
+        *self = out;
+    }
+}
+fn put_varint(out: &mut Vec<u8>, mut n: usize) {
+    while n >= 0x80 {
+        out.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+fn get_varint(bytes: &[u8], read: &mut usize) -> Result<usize, String> {
+    let mut n = 0usize;
+    for shift in (0..usize::BITS).step_by(7) {
+        let b = *bytes.get(*read).ok_or("RunList delta is truncated")?;
+        *read += 1;
+        n |= ((b & 0x7f) as usize) << shift;
+        if b & 0x80 == 0 {
+            return Ok(n);
+        }
+    }
+    Err("RunList delta has an overlong varint".into())
 }
+/// `RunList`s are equal if they contain the same ids, regardless of how the runs are split up.
+impl<M: TableMarker> PartialEq for RunList<M> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.difference(other).is_empty()
+    }
+}
+impl<M: TableMarker> Eq for RunList<M> {}
 // FIXME: Ugh! IntoIterator for RunList. Do I want it? I actually don't use RunList directly very often...
 impl<'a, M: TableMarker> IntoIterator for &'a RunList<M> {
     type Item = Id<M>;
@@ -875,7 +1593,7 @@ impl<'a, M: TableMarker> Iterator for RunListIterRangesInclusive<'a, M> {
 
 #[cfg(feature = "bincode")]
 mod bincode_impls {
-    use super::{Id, RunList, TableMarker};
+    use super::{Check, Id, IdList, RunList, TableMarker};
     use bincode::enc::{Encoder, Encode};
     use bincode::de::{Decoder, Decode};
     use bincode::error::{EncodeError, DecodeError};
@@ -917,6 +1635,26 @@ mod bincode_impls {
             }
         }
     }
+    /// Only the live ids, the capacity, and whether the list is dense are saved. Pending pushes
+    /// and deletes are lost, and the order in which the free ids are recycled may change.
+    impl<M: TableMarker> Encode for IdList<M> {
+        fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+            self.live_ids().encode(encoder)?;
+            self.outer_capacity().encode(encoder)?;
+            self.dense.encode(encoder)
+        }
+    }
+    impl<M: TableMarker> Decode for IdList<M> {
+        fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+            let live = RunList::<M>::decode(decoder)?;
+            let capacity = usize::decode(decoder)?;
+            let dense = bool::decode(decoder)?;
+            let mut list = IdList::<M>::default();
+            list.load_live(&live, capacity).map_err(DecodeError::OtherString)?;
+            list.dense = dense;
+            Ok(list)
+        }
+    }
 }
 
 #[cfg(test)]
@@ -997,6 +1735,18 @@ mod test_run_list {
         assert_eq!(got_len, 1);
     }
     #[test]
+    fn zip_range() {
+        let r = UncheckedIdRange::<M>::new(Id(3), Id(6));
+        let got: Vec<(I, char)> = r.zip("abc".chars()).collect();
+        assert_eq!(got, vec![(Id(3), 'a'), (Id(4), 'b'), (Id(5), 'c')]);
+    }
+    #[test]
+    #[should_panic]
+    fn zip_range_short() {
+        let r = UncheckedIdRange::<M>::new(Id(3), Id(6));
+        r.zip(0..2).for_each(drop);
+    }
+    #[test]
     fn on_iter_is_some() {
         let r = UncheckedIdRange::<M>::on(Id::<M>::from_usize(3));
         assert_eq!(1, r.iter().count());
@@ -1111,6 +1861,308 @@ mod test_run_list {
         }
     }
 
+    #[test]
+    fn shrink_to_fit() {
+        let mut l = RunList::<M>::default();
+        for i in 0..100 {
+            l.push(Id(i * 2));
+        }
+        l.clear();
+        l.push_run(Id(0)..=Id(9));
+        let before = l.clone();
+        l.shrink_to_fit();
+        assert_eq!(l, before);
+        assert_eq!(l.get_data(), before.get_data());
+    }
+
+    #[test]
+    fn eq_ignores_runs() {
+        let mut a = RunList::<M>::default();
+        a.push_run(Id(0)..=Id(4));
+        let mut b = RunList::<M>::default();
+        b.push_run(Id(0)..=Id(1));
+        b.push_run(Id(2)..=Id(4));
+        assert_eq!(a, b);
+        b.push(Id(9));
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn id_list_eq_recycled() {
+        let universe = Universe::new();
+        let mut a = IdList::<M>::default();
+        let _ = unsafe { a.recycle_ids_contiguous(10, false) };
+        a.flush(&universe);
+        a.delete(Id(5));
+        a.delete(Id(2));
+        a.flush(&universe);
+        for _ in 0..2 {
+            let _ = unsafe { a.recycle_id(false) };
+        }
+        a.flush(&universe);
+        let mut b = IdList::<M>::default();
+        let mut live = RunList::<M>::new();
+        live.push_run(Id(0)..=Id(9));
+        b.load_live(&live, 10).unwrap();
+        assert_eq!(a, b);
+        b.delete(Id(7));
+        b.flush(&universe);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn diff() {
+        let mut old = RunList::<M>::default();
+        old.push_run(Id(0)..=Id(9));
+        old.push_run(Id(20)..=Id(29));
+        let mut new = RunList::<M>::default();
+        new.push_run(Id(5)..=Id(24));
+        new.push(Id(40));
+        let (added, removed) = old.diff(&new);
+        let mut expect_added = RunList::<M>::default();
+        expect_added.push_run(Id(10)..=Id(19));
+        expect_added.push(Id(40));
+        let mut expect_removed = RunList::<M>::default();
+        expect_removed.push_run(Id(0)..=Id(4));
+        expect_removed.push_run(Id(25)..=Id(29));
+        assert_eq!(added, expect_added);
+        assert_eq!(removed, expect_removed);
+        assert_eq!(old.diff(&old), (RunList::new(), RunList::new()));
+    }
+
+    #[test]
+    fn range_chunks() {
+        let r = |start: u8, end: u8| IdRange::new(Id::<M>(start), Id(end));
+        let chunks: Vec<_> = r(0, 10).chunks(3).collect();
+        assert_eq!(chunks, vec![r(0, 3), r(3, 6), r(6, 9), r(9, 10)]);
+        assert_eq!(r(2, 8).chunks(3).collect::<Vec<_>>(), vec![r(2, 5), r(5, 8)]);
+        assert_eq!(r(4, 4).chunks(3).count(), 0);
+    }
+
+    #[test]
+    fn range_algebra() {
+        let r = |start: u8, end: u8| IdRange::new(Id::<M>(start), Id(end));
+        assert_eq!(r(0, 5).intersect(&r(3, 8)), Some(r(3, 5)));
+        assert_eq!(r(3, 8).intersect(&r(0, 5)), Some(r(3, 5)));
+        assert_eq!(r(0, 8).intersect(&r(2, 4)), Some(r(2, 4)));
+        // Exclusive ends: adjacent ranges share nothing.
+        assert_eq!(r(0, 3).intersect(&r(3, 6)), None);
+        assert_eq!(r(0, 3).intersect(&r(5, 6)), None);
+        assert_eq!(r(2, 2).intersect(&r(0, 6)), None);
+
+        assert_eq!(r(0, 3).union_contiguous(&r(3, 6)), Some(r(0, 6)));
+        assert_eq!(r(3, 6).union_contiguous(&r(0, 3)), Some(r(0, 6)));
+        assert_eq!(r(0, 5).union_contiguous(&r(2, 8)), Some(r(0, 8)));
+        assert_eq!(r(0, 8).union_contiguous(&r(2, 4)), Some(r(0, 8)));
+        assert_eq!(r(0, 3).union_contiguous(&r(4, 6)), None);
+        assert_eq!(r(0, 3).union_contiguous(&r(9, 9)), Some(r(0, 3)));
+    }
+
+    #[test]
+    fn diff_edges() {
+        let list = |runs: &[(u8, u8)]| {
+            let mut l = RunList::<M>::default();
+            for &(a, b) in runs {
+                l.push_run(Id(a)..=Id(b));
+            }
+            l
+        };
+        // Adjacent.
+        let (added, removed) = list(&[(0, 4)]).diff(&list(&[(5, 9)]));
+        assert_eq!(added, list(&[(5, 9)]));
+        assert_eq!(removed, list(&[(0, 4)]));
+        // Nested.
+        let (added, removed) = list(&[(0, 9)]).diff(&list(&[(3, 5)]));
+        assert_eq!(added, RunList::new());
+        assert_eq!(removed, list(&[(0, 2), (6, 9)]));
+        let (added, removed) = list(&[(3, 5)]).diff(&list(&[(0, 9)]));
+        assert_eq!(added, list(&[(0, 2), (6, 9)]));
+        assert_eq!(removed, RunList::new());
+        // Disjoint.
+        let (a, b) = (list(&[(0, 1), (10, 11)]), list(&[(3, 4), (20, 20)]));
+        assert_eq!(a.diff(&b), (b.clone(), a.clone()));
+        assert_eq!(RunList::new().diff(&a), (a.clone(), RunList::new()));
+    }
+
+    #[test]
+    fn from_sorted_iter() {
+        #[derive(Debug, Copy, Clone, Default)]
+        struct W;
+        impl TableMarker for W {
+            const NAME: Name = "W";
+            type RawId = u32;
+            fn header() -> TableHeader { unimplemented!() }
+        }
+        impl Register for W {
+            fn register(_universe: &mut Universe) { unimplemented!() }
+        }
+        let l = RunList::<W>::from_sorted_iter((0..1000).map(Id)).unwrap();
+        assert_eq!(l.get_data(), &[[0, 999]]);
+        assert_eq!(l.len(), 1000);
+        let l = RunList::<M>::from_sorted_iter(vec![Id(1), Id(2), Id(5), Id(7), Id(8)]).unwrap();
+        assert_eq!(l.get_data(), &[[1, 2], [5, 5], [7, 8]]);
+        assert!(RunList::<M>::from_sorted_iter(None).unwrap().is_empty());
+        assert!(RunList::<M>::from_sorted_iter(vec![Id(1), Id(2), Id(2)]).is_err());
+        assert!(RunList::<M>::from_sorted_iter(vec![Id(4), Id(3)]).is_err());
+    }
+
+    #[test]
+    fn merge() {
+        let mut a = RunList::<M>::default();
+        a.push_run(Id(0)..=Id(4));
+        a.push_run(Id(20)..=Id(29));
+        a.push(Id(50));
+        let mut b = RunList::<M>::default();
+        b.push_run(Id(5)..=Id(9));
+        b.push_run(Id(15)..=Id(22));
+        b.push_run(Id(25)..=Id(40));
+        b.push(Id(60));
+        a.merge(&b);
+        assert_eq!(a.get_data(), &[[0, 9], [15, 40], [50, 50], [60, 60]]);
+        let before = a.clone();
+        a.merge(&RunList::new());
+        assert_eq!(a.get_data(), before.get_data());
+        let mut empty = RunList::<M>::new();
+        empty.merge(&before);
+        assert_eq!(empty.get_data(), before.get_data());
+    }
+
+    #[cfg(feature = "bincode")]
+    #[test]
+    fn bincode_id_list() {
+        let universe = Universe::new();
+        let mut list = IdList::<M>::default();
+        let _ = unsafe { list.recycle_ids_contiguous(20, false) };
+        list.flush(&universe);
+        for i in &[0u8, 3, 4, 5, 11, 19] {
+            list.delete(Id(*i));
+        }
+        list.flush(&universe);
+        let config = bincode::config::Configuration::standard();
+        let bytes = bincode::encode_to_vec(&list, config).unwrap();
+        let decoded: IdList<M> = bincode::decode_from_slice(&bytes, config).unwrap();
+        let ids = |list: &IdList<M>| list.iter().map(|id| id.uncheck()).collect::<Vec<_>>();
+        assert_eq!(ids(&decoded), ids(&list));
+        assert_eq!(decoded.outer_capacity(), 20);
+        assert_eq!(bincode::encode_to_vec(&decoded, config).unwrap(), bytes);
+    }
+
+    #[cfg(feature = "bincode")]
+    #[test]
+    fn bincode_id_list_recycled() {
+        let universe = Universe::new();
+        let mut list = IdList::<M>::default();
+        let _ = unsafe { list.recycle_ids_contiguous(20, false) };
+        list.flush(&universe);
+        for i in &[2u8, 9, 10, 15] {
+            list.delete(Id(*i));
+        }
+        list.flush(&universe);
+        for _ in 0..3 {
+            let _ = unsafe { list.recycle_id(false) };
+        }
+        list.flush(&universe);
+        let config = bincode::config::Configuration::standard();
+        let bytes = bincode::encode_to_vec(&list, config).unwrap();
+        let decoded: IdList<M> = bincode::decode_from_slice(&bytes, config).unwrap();
+        let ids = |list: &IdList<M>| list.sorted_iter().map(|id| id.uncheck()).collect::<Vec<_>>();
+        assert_eq!(ids(&decoded), ids(&list));
+        assert_eq!(decoded.len(), 19);
+        assert_eq!(decoded.outer_capacity(), 20);
+    }
+
+    #[test]
+    fn debug_summary() {
+        let mut l = RunList::<M>::default();
+        for i in (10..=210).step_by(2) {
+            l.push(Id(i));
+        }
+        assert_eq!(format!("{:?}", l), "[101 ids, 101 runs, 10..=210]");
+        assert!(format!("{:#?}", l).len() > 100);
+        let mut small = RunList::<M>::default();
+        small.push_run(Id(0)..=Id(200));
+        assert_eq!(format!("{:?}", small), format!("[{:?}]", small.inner));
+    }
+
+    #[test]
+    fn delta() {
+        let mut lists = vec![RunList::<M>::default()];
+        let mut l = RunList::<M>::default();
+        l.push_run(Id(3)..=Id(9));
+        l.push(Id(200));
+        lists.push(l);
+        let mut l = RunList::<M>::default();
+        l.push_run(Id(0)..=Id(4));
+        l.push_run(Id(8)..=Id(150));
+        lists.push(l);
+        lists.push(RunList::default());
+        let mut remote = RunList::<M>::default();
+        for pair in lists.windows(2) {
+            let mut bytes = vec![];
+            pair[1].encode_delta(&pair[0], &mut bytes);
+            assert_eq!(remote.apply_delta(&bytes), Ok(bytes.len()));
+            assert_eq!(remote, pair[1]);
+            assert_eq!(remote.get_data(), pair[1].get_data());
+        }
+        assert!(remote.apply_delta(&[1, 0]).is_err());
+    }
+
+    #[test]
+    fn iter_gaps() {
+        let mut l = RunList::<M>::default();
+        l.push_run(Id(0)..=Id(2));
+        l.push_run(Id(5)..=Id(6));
+        let gaps: Vec<_> = l
+            .iter_gaps(IdRange::new(Id(0), Id(10)))
+            .map(|r| (r.start.0, r.end.0))
+            .collect();
+        assert_eq!(gaps, vec![(3, 5), (7, 10)]);
+        let gaps: Vec<_> = l
+            .iter_gaps(IdRange::new(Id(1), Id(6)))
+            .map(|r| (r.start.0, r.end.0))
+            .collect();
+        assert_eq!(gaps, vec![(3, 5)]);
+        assert_eq!(RunList::<M>::new().iter_gaps(IdRange::new(Id(2), Id(4))).count(), 1);
+    }
+
+    #[test]
+    fn checked_add() {
+        let mut l = IdList::<M>::default();
+        let u = &Universe::new();
+        unsafe {
+            for _ in 0..3 {
+                let _ = l.recycle_id(true);
+            }
+        }
+        l.flush(u);
+        let first = l.iter().next().unwrap();
+        assert_eq!(first.checked_add(2, &l).map(|i| i.uncheck()), Some(Id(2)));
+        assert_eq!(first.checked_add(3, &l), None);
+        assert_eq!(first.checked_add(usize::MAX, &l), None);
+        assert_eq!(first.checked_sub(1, &l), None);
+        let last = first.checked_add(2, &l).unwrap();
+        assert_eq!(last.checked_sub(2, &l), Some(first));
+    }
+
+    #[test]
+    fn free_runs() {
+        let mut l = IdList::<M>::default();
+        let u = &Universe::new();
+        unsafe {
+            for _ in 0..10 {
+                let _ = l.recycle_id(true);
+            }
+        }
+        l.flush(u);
+        assert_eq!((l.free_len(), l.free_runs()), (0, 0));
+        for &i in &[0, 3, 4, 7] {
+            l.delete(Id(i));
+        }
+        l.flush(u);
+        assert_eq!(l.free_len(), 4);
+        assert_eq!(l.free_runs(), 3);
+    }
+
     #[test]
     fn dude2() {
         let mut l = RunList::<M>::default();
@@ -1125,3 +2177,7 @@ mod test_run_list {
 fn oob(i: usize, max: usize) -> ! {
     panic!("OOB: i:{} >= max:{}", i, max)
 }
+#[cold]
+fn exhausted<M: TableMarker>() -> ! {
+    panic!("table {} exhausted its id space (RawId::LAST reached)", M::NAME)
+}
@@ -4,9 +4,13 @@ use crate::prelude_lib::*;
 use std::any::Any as StdAny;
 use std::borrow::Cow;
 use std::cell::Cell;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::future::Future;
 use std::panic::Location;
+use std::sync::{Arc, MutexGuard};
+use std::task::{Context, Poll, Wake, Waker};
+use std::time::{Duration, Instant};
 
 fn describe_resources(resources: &[(Ty, Access)]) {
     if resources.is_empty() {
@@ -19,63 +23,74 @@ fn describe_resources(resources: &[(Ty, Access)]) {
             Access::Read  => "read  ",
             Access::Write => "write ",
         };
-        let mut ty = format!("{:?}", ty);
-        let pretty = &[
-            // Stolen from ezty... hmm.
-            ("alloc::boxed::", "Box"),
-            ("alloc::collections::binary_heap::BinaryHeap", "BinaryHeap"),
-            ("alloc::collections::btree::map::BTreeMap", "BTreeMap"),
-            ("alloc::collections::btree::set::BTreeSet", "BTreeSet"),
-            ("alloc::collections::linked_list::LinkedList", "LinkedList"),
-            ("alloc::collections::vec_deque::VecDeque", "VecDeque"),
-            ("alloc::sync::Arc", "Arc"),
-            ("alloc::vec::Vec", "Vec"),
-            ("core::cell::Cell", "Cell"),
-            ("core::cell::RefCell", "RefCell"),
-            ("core::option::Option", "Option"),
-            ("core::result::Result", "Result"),
-            ("std::collections::hash::map::HashMap", "HashMap"),
-            ("std::collections::hash::set::HashSet", "HashSet"),
-            ("std::sync::rwlock::RwLock", "RwLock"),
-            ("alloc::string::String", "String"),
-            // And more stuff
-            ("v9::column::Column", "Column"),
-            ("::in_v9::", "::"),
-            ("::_v9_property_mod_", "::"),
-            ("::PropGeneric<", "::<"),
-            ("v9::id::IdList", "IdList"),
-            ("v9::id::", ""),
-            ("v9::column::Column", "Column"),
-            ("v9::event::", "v9:"),
-            ("v9::linkage::", "v9:"),
-            // Just deal with it, I guess.
-            ("lerp::Lerp", "Lerp"),
-            ("nalgebra::base::dimension::", ""),
-            ("nalgebra::base::matrix::Matrix<f32, U3, U1, nalgebra::base::array_storage::ArrayStorage<f32, U3, U1>>", "V3"),
-            ("nalgebra::base::unit::Unit", "Unit"),
-            ("new_units::", ""),
-            ("space::rad::Rad", "Rad"),
-            ("triton::", ""),
-            ("triton::behaviors::QuatrexDefinition", "QuatrexDefinition"),
-            ("Unit<nalgebra::geometry::quaternion::Quaternion<f32>>", "Quat"),
-            ("util::tagdb::Tag", "Tag"),
-        ];
-        for (ugly, pretty) in pretty {
-            ty = ty.replace(ugly, pretty);
-        }
+        let ty = pretty_ty(*ty);
         eprintln!("\t\t\t{} {}", a, ty);
     }
 }
 
+/// `ty`'s name, with the more tedious paths shortened.
+pub fn pretty_ty(ty: Ty) -> String {
+    let mut name = format!("{:?}", ty);
+    let pretty = &[
+        // Stolen from ezty... hmm.
+        ("alloc::boxed::", "Box"),
+        ("alloc::collections::binary_heap::BinaryHeap", "BinaryHeap"),
+        ("alloc::collections::btree::map::BTreeMap", "BTreeMap"),
+        ("alloc::collections::btree::set::BTreeSet", "BTreeSet"),
+        ("alloc::collections::linked_list::LinkedList", "LinkedList"),
+        ("alloc::collections::vec_deque::VecDeque", "VecDeque"),
+        ("alloc::sync::Arc", "Arc"),
+        ("alloc::vec::Vec", "Vec"),
+        ("core::cell::Cell", "Cell"),
+        ("core::cell::RefCell", "RefCell"),
+        ("core::option::Option", "Option"),
+        ("core::result::Result", "Result"),
+        ("std::collections::hash::map::HashMap", "HashMap"),
+        ("std::collections::hash::set::HashSet", "HashSet"),
+        ("std::sync::rwlock::RwLock", "RwLock"),
+        ("alloc::string::String", "String"),
+        // And more stuff
+        ("v9::column::Column", "Column"),
+        ("::in_v9::", "::"),
+        ("::_v9_property_mod_", "::"),
+        ("::PropGeneric<", "::<"),
+        ("v9::id::IdList", "IdList"),
+        ("v9::id::", ""),
+        ("v9::column::Column", "Column"),
+        ("v9::event::", "v9:"),
+        ("v9::linkage::", "v9:"),
+        // Just deal with it, I guess.
+        ("lerp::Lerp", "Lerp"),
+        ("nalgebra::base::dimension::", ""),
+        ("nalgebra::base::matrix::Matrix<f32, U3, U1, nalgebra::base::array_storage::ArrayStorage<f32, U3, U1>>", "V3"),
+        ("nalgebra::base::unit::Unit", "Unit"),
+        ("new_units::", ""),
+        ("space::rad::Rad", "Rad"),
+        ("triton::", ""),
+        ("triton::behaviors::QuatrexDefinition", "QuatrexDefinition"),
+        ("Unit<nalgebra::geometry::quaternion::Quaternion<f32>>", "Quat"),
+        ("util::tagdb::Tag", "Tag"),
+    ];
+    for (ugly, pretty) in pretty {
+        name = name.replace(ugly, pretty);
+    }
+    name
+}
+
 #[must_use]
 pub struct ResetBuffer<'a> {
     pub(crate) universe: &'a Universe,
     pub name: &'a KernelName,
     buffer: &'a mut LockBuffer,
+    held: usize,
+    /// Set once `cleanup` has released the locks, so that a later panic doesn't release them
+    /// again.
+    released: Cell<bool>,
 }
 impl Drop for ResetBuffer<'_> {
     fn drop(&mut self) {
-        if std::thread::panicking() {
+        pop_held(self.held);
+        if std::thread::panicking() && !self.released.get() {
             eprintln!("NOTE: Panic in kernel {}", self.name);
             describe_resources(&self.buffer.resources);
             let mut objects = self.universe.objects.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
@@ -96,14 +111,87 @@ impl<'a> ResetBuffer<'a> {
         // The cleanup closure.
         // See comment in 'fn run' KernelFn impl.
         let mut objects = self.universe.objects.lock().expect("unable to release locks");
+        #[cfg(debug_assertions)]
+        let mismatch = check_lengths(self.universe, &objects, &self.buffer.resources);
+        #[cfg(not(debug_assertions))]
+        let mismatch: Option<LengthMismatch> = None;
         for &(ty, acc) in &self.buffer.resources {
             let lock = objects.get_mut(&ty).expect("lost locked object");
-            lock.release(acc);
+            match &mismatch {
+                // The table is broken, so keep anyone else from using it.
+                Some((_, _, parts)) if acc == Access::Write && parts.contains(&ty) => lock.state = LockState::Poison,
+                _ => lock.release(acc),
+            }
         }
+        self.released.set(true);
+        pop_held(self.held);
+        drop(objects);
         self.universe.condvar.notify_all();
+        if let Some((table, lengths, _)) = mismatch {
+            panic!("kernel {} left table {} with columns of different lengths: {:?}", self.name, table, lengths);
+        }
         PostCleanup { name: self.name, buffer: self.buffer }
     }
 }
+/// The table's name, the lengths that were compared, and the parts they came from.
+type LengthMismatch = (Name, Vec<(Name, usize)>, Vec<Ty>);
+/// Finds a table that the kernel left with columns of different lengths. Every table that the
+/// kernel write-locked part of is checked; its other parts are compared too, unless another
+/// thread has them write-locked.
+#[cfg(debug_assertions)]
+fn check_lengths(
+    universe: &Universe,
+    objects: &HashMap<Ty, Box<Locked>>,
+    resources: &[(Ty, Access)],
+) -> Option<LengthMismatch> {
+    let readable = |ty: &Ty| {
+        resources.iter().any(|(t, _)| t == ty) || match objects.get(ty) {
+            Some(lock) => matches!(lock.state, LockState::Open | LockState::Read(_)),
+            None => false,
+        }
+    };
+    let mut markers = vec![];
+    for (ty, acc) in resources {
+        if *acc != Access::Write { continue; }
+        if let Some(&marker) = universe.table_parts.get(ty) {
+            if !markers.contains(&marker) {
+                markers.push(marker);
+            }
+        }
+    }
+    for marker in markers {
+        let header = match objects.get(&marker) {
+            // Nothing writes to a header, but don't count on it.
+            Some(lock) if !matches!(lock.state, LockState::Write(_) | LockState::Poison) => lock,
+            _ => continue,
+        };
+        let header = match unsafe { (*header.obj.get()).downcast_ref::<TableHeader>() } {
+            Some(header) => header,
+            None => continue,
+        };
+        let len_of = |ty: &Ty, len: fn(&dyn AnyDebug) -> usize| {
+            let lock = &objects[ty];
+            let obj: &dyn AnyDebug = unsafe { &**lock.obj.get() };
+            len(obj)
+        };
+        let mut lengths = vec![];
+        let mut parts = vec![];
+        if readable(&header.ids) {
+            lengths.push(("ids", len_of(&header.ids, header.ids_capacity)));
+            parts.push(header.ids);
+        }
+        for col in &header.columns {
+            if readable(&col.column_type) {
+                lengths.push((col.name, len_of(&col.column_type, col.len)));
+                parts.push(col.column_type);
+            }
+        }
+        if lengths.iter().any(|&(_, len)| len != lengths[0].1) {
+            return Some((header.name, lengths, parts));
+        }
+    }
+    None
+}
 pub struct PostCleanup<'a> {
     pub name: &'a KernelName,
     buffer: &'a LockBuffer,
@@ -117,51 +205,155 @@ impl Drop for PostCleanup<'_> {
     }
 }
 
+/// Returned by `Universe::try_run` when a resource was locked by someone else.
+#[derive(Debug, Clone)]
+pub struct LockContention {
+    pub kernel: String,
+    pub ty: Ty,
+}
+impl fmt::Display for LockContention {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "kernel {} couldn't lock {:?}", self.kernel, self.ty)
+    }
+}
+impl std::error::Error for LockContention {}
+
+/// Collects the locks for `buffer` into `buffer.locks`, unless one of them isn't available.
+fn find_blocked(name: &KernelName, objects: &mut HashMap<Ty, Box<Locked>>, buffer: &mut LockBuffer) -> Option<Ty> {
+    let locks = &mut buffer.locks;
+    let resources = &buffer.resources;
+    locks.clear();
+    // `vals.clear()` goes elsewhere so that it can be used to pass in additional arguments.
+    for (argn, &(ty, acc)) in resources.iter().enumerate() {
+        let lock = objects
+            .get_mut(&ty)
+            .unwrap_or_else(|| {
+                panic!("kernel {} argument component {} (of {}) has unknown type {:?}", name, argn, resources.len(), ty)
+            });
+        if !lock.can(acc) {
+            return Some(ty);
+        }
+        locks.push((lock.deref_mut() as *mut Locked, acc));
+    }
+    None
+}
+
 impl Universe {
     pub fn run(&self, kernel: &mut Kernel) {
         self.run_return::<()>(kernel)
     }
+    /// Runs a kernel, but panics first if it would take any write locks.
+    ///
+    /// Note that a kernel taking `UniverseRef` can still do whatever it likes.
+    pub fn run_readonly(&self, kernel: &mut Kernel) {
+        if let Some((ty, _)) = kernel.resources().iter().find(|(_, acc)| *acc == Access::Write) {
+            panic!("kernel {} passed to run_readonly() wants to write {:?}", kernel.name, ty);
+        }
+        self.run(kernel)
+    }
+    /// Runs a kernel if all of its locks are available right now. Otherwise nothing is run, and
+    /// the first resource that was in use is returned.
+    pub fn try_run(&self, kernel: &mut Kernel) -> Result<(), LockContention> {
+        let mut ret: Option<()> = None;
+        unsafe {
+            let mut cleanup = self.try_prepare_buffer(&kernel.name, &mut kernel.buffer)?;
+            self.execute_from_buffer(
+                &mut kernel.run,
+                &mut ret,
+                &mut cleanup,
+            );
+            cleanup.done();
+        }
+        ret.expect("return value not set");
+        Ok(())
+    }
+    /// Checks that every resource `kernel` wants is in the universe, so that it can be reported
+    /// nicely before running, rather than panicking when the kernel is run.
+    pub fn validate_kernel(&self, kernel: &Kernel) -> Result<(), String> {
+        let missing: Vec<String> = kernel
+            .resources()
+            .iter()
+            .filter(|&&(ty, _)| !self.has_ty(ty))
+            .map(|&(ty, _)| pretty_ty(ty))
+            .collect();
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("kernel {} wants missing objects: {}", kernel.name, missing.join(", ")))
+        }
+    }
+    /// Like `try_run`, but makes up to `attempts` tries (at least one), sleeping for `backoff`
+    /// between them. Unlike `run`, this gives up eventually, so it's suited to latency-sensitive
+    /// work. The last contention is returned.
+    pub fn run_retry(&self, kernel: &mut Kernel, attempts: usize, backoff: Duration) -> Result<(), LockContention> {
+        let mut attempts = attempts.max(1);
+        loop {
+            let err = match self.try_run(kernel) {
+                Ok(()) => return Ok(()),
+                Err(err) => err,
+            };
+            attempts -= 1;
+            if attempts == 0 {
+                return Err(err);
+            }
+            std::thread::sleep(backoff);
+        }
+    }
     pub fn run_return<Ret: StdAny>(&self, kernel: &mut Kernel) -> Ret {
         let mut ret: Option<Ret> = None;
         self.run_and_return_into(kernel, (&mut ret) as &mut dyn StdAny);
         ret.expect("return value not set")
     }
     unsafe fn prepare_buffer<'a>(&'a self, name: &'a KernelName, buffer: &'a mut LockBuffer) -> ResetBuffer<'a> {
+        // Waiting on ourselves would never finish.
+        assert_not_held(&format_args!("kernel {}", name), &buffer.resources);
+        let started = self.lock_telemetry.as_ref().map(|_| Instant::now());
         let objects = self.objects.lock().expect("prepare_buffer locking objects failed");
-        let _objects = self.condvar.wait_while(objects, |objects| {
-            let locks = &mut buffer.locks;
-            let resources = &mut buffer.resources;
-            locks.clear();
-            // `vals.clear()` goes below so that it can be used to pass in additional arguments.
-            resources
-                .iter()
-                .enumerate()
-                .any(|(argn, &(ty, acc))| {
-                    let lock = objects
-                        .get_mut(&ty)
-                        .unwrap_or_else(|| {
-                            panic!("kernel {} argument component {} (of {}) has unknown type {:?}", name, argn, resources.len(), ty)
-                        });
-                    if !lock.can(acc) {
-                        true
-                    } else {
-                        locks.push((lock.deref_mut() as *mut Locked, acc));
-                        false
-                    }
-                })
+        let objects = self.condvar.wait_while(objects, |objects| {
+            find_blocked(name, objects, buffer).is_some()
         }).expect("prepare_buffer condvar wait failed");
+        let waited = started.map(|started| started.elapsed());
+        self.acquire_buffer(name, buffer, objects, waited)
+    }
+    /// Like `prepare_buffer`, but gives up instead of waiting.
+    unsafe fn try_prepare_buffer<'a>(&'a self, name: &'a KernelName, buffer: &'a mut LockBuffer) -> Result<ResetBuffer<'a>, LockContention> {
+        let mut objects = self.objects.lock().expect("try_prepare_buffer locking objects failed");
+        // Nothing has been acquired yet, so there's nothing to undo.
+        if let Some(ty) = find_blocked(name, &mut objects, buffer) {
+            return Err(LockContention {
+                kernel: name.name.to_string(),
+                ty,
+            });
+        }
+        Ok(self.acquire_buffer(name, buffer, objects, None))
+    }
+    /// Acquires the locks that `find_blocked` found to be available.
+    unsafe fn acquire_buffer<'a>(
+        &'a self,
+        name: &'a KernelName,
+        buffer: &'a mut LockBuffer,
+        objects: MutexGuard<HashMap<Ty, Box<Locked>>>,
+        waited: Option<Duration>,
+    ) -> ResetBuffer<'a> {
         for &mut (lock, acc) in &mut buffer.locks {
             let lock: &mut Locked = &mut *lock;
-            lock.acquire(acc);
+            lock.acquire_for(acc, name);
             let obj: *mut dyn AnyDebug = lock.contents();
             let obj: &mut dyn AnyDebug = &mut *obj;
             let obj: *mut dyn AnyDebug = obj;
             buffer.vals.push((obj, acc));
         }
+        drop(objects);
+        if let (Some(sink), Some(waited)) = (&self.lock_telemetry, waited) {
+            sink(&name.name, waited);
+        }
+        let held = push_held(&buffer.resources);
         ResetBuffer {
             universe: self,
             name,
             buffer,
+            held,
+            released: Cell::new(false),
         }
     }
     unsafe fn execute_from_buffer<F>(
@@ -217,6 +409,74 @@ impl Universe {
         }
     }
 
+    /// Like `eval`, but the kernel is an `async fn`. The future is driven to completion by a
+    /// simple `block_on` on this thread, and the locks are held across every `.await`.
+    /// (A closure returning an `async move` block that uses its arguments won't typecheck, as
+    /// the future's lifetime can't be named.)
+    ///
+    /// This means that the kernel blocks every other kernel wanting its resources for as long as
+    /// it is waiting on I/O, and that it deadlocks if anything it awaits needs them. It is meant
+    /// for single-threaded or carefully scheduled use, such as loading assets into a table.
+    /// Kernels taking no arguments aren't supported.
+    #[track_caller]
+    pub fn eval_async<Dump, Fut, K>(&self, k: K) -> Fut::Output
+    where
+        K: KernelFnAsync<Dump, Fut>,
+        Fut: Future,
+    {
+        let name = KernelName {
+            name: std::any::type_name::<K>().into(),
+            location: Location::caller(),
+        };
+        let ret = Cell::new(Option::<Fut::Output>::None);
+        let run = |rez: Rez, _ret: &mut dyn StdAny, cleanup: &mut ResetBuffer| {
+            let got = unsafe { k.run(rez, cleanup) };
+            ret.set(Some(got));
+        };
+        unsafe {
+            let mut buffer = LockBuffer::new::<Dump, Fut, K>();
+            let mut cleanup = self.prepare_buffer(&name, &mut buffer);
+            self.execute_from_buffer(
+                run,
+                &mut (),
+                &mut cleanup,
+            );
+            cleanup.done();
+            ret.into_inner().take().expect("return value not set")
+        }
+    }
+    /// Lock `resources` and call `run`, without the generic front door of `eval`. This avoids
+    /// monomorphizing a kernel per closure, which matters if you have hundreds of small ones.
+    ///
+    /// `run` must pull the values out of the `Rez` itself, in the order of `resources`, using
+    /// `take_ref` for `Access::Read` and `take_mut` for `Access::Write`. `Extract::Cleanup` is
+    /// *not* run, so eg an `IdList` that was pushed to must be flushed by hand afterwards, and
+    /// edits won't be logged.
+    #[track_caller]
+    pub fn run_dyn(&self, resources: &[(Ty, Access)], run: &mut dyn FnMut(Rez, &Universe)) {
+        let name = KernelName {
+            name: "run_dyn".into(),
+            location: Location::caller(),
+        };
+        let mut buffer = LockBuffer::new0(|f| {
+            for &(ty, acc) in resources {
+                f(ty, acc);
+            }
+        });
+        unsafe {
+            let mut cleanup = self.prepare_buffer(&name, &mut buffer);
+            self.execute_from_buffer(
+                |rez: Rez, _ret: &mut dyn StdAny, cleanup: &mut ResetBuffer| {
+                    run(rez, self);
+                    cleanup.cleanup();
+                },
+                &mut (),
+                &mut cleanup,
+            );
+            cleanup.done();
+        }
+    }
+
     /// Quick & dirty `Kernel` `run`ner. This is provided to simplify tests.
     // FIXME: Delete this.
     pub fn kmap<Dump, K>(&self, k: K)
@@ -236,6 +496,165 @@ impl Universe {
     {
         self.run_return::<Ret>(&mut Kernel::new(k))
     }
+    /// Run `f` on `chunks` threads at once. Each thread gets a disjoint range of the ids of `M`,
+    /// and a `Chunk` of each argument that only allows access to that range. The ranges cover
+    /// every row, including removed ones; take `&IdList<M>` and check `exists` if that matters.
+    ///
+    /// ```
+    /// # use v9::prelude_lib::*;
+    /// # use v9::column::EditChunk;
+    /// v9::decl_table! {
+    ///     pub struct cells {
+    ///         pub energy: u32,
+    ///     }
+    /// }
+    /// # fn main() {
+    /// # let mut universe = Universe::new();
+    /// # cells::Marker::register(&mut universe);
+    /// universe.run_par(4, |range: cells::Range, mut energy: EditChunk<cells::Marker, u32>| {
+    ///     for id in range {
+    ///         energy[id] += 1;
+    ///     }
+    /// });
+    /// # }
+    /// ```
+    pub fn run_par<M, P, F>(&self, chunks: usize, f: F)
+    where
+        M: TableMarker,
+        P: Chunk<M>,
+        F: Fn(IdRange<'static, Id<M>>, P) + Sync,
+    {
+        let chunks = chunks.max(1);
+        self.eval(|mut whole: P::Whole, ids: &IdList<M>| {
+            let capacity = ids.outer_capacity();
+            let per = ((capacity + chunks - 1) / chunks).max(1);
+            let f = &f;
+            std::thread::scope(|scope| {
+                let mut threads = vec![];
+                for range in IdRange::to(Id::from_usize(capacity)).chunks(per) {
+                    let chunk = unsafe { P::chunk(&mut whole, range) };
+                    threads.push(scope.spawn(move || f(range, chunk)));
+                }
+                // Joining them ourselves keeps the original panic message.
+                for thread in threads {
+                    if let Err(panic) = thread.join() {
+                        std::panic::resume_unwind(panic);
+                    }
+                }
+            });
+        })
+    }
+}
+
+/// A kernel argument for `Universe::run_par`, restricted to one range of ids so that the
+/// threads can't step on each other. Implemented for `ReadColumn`, `EditChunk`, `&IdList`, and
+/// tuples of those.
+pub unsafe trait Chunk<M: TableMarker>: Send + Sized {
+    /// The kernel argument that gets split up.
+    type Whole: Extract;
+    /// # Safety
+    /// The chunks made from disjoint ranges must not overlap mutably.
+    unsafe fn chunk(whole: &mut Self::Whole, range: IdRange<'static, Id<M>>) -> Self;
+}
+unsafe impl<'a, M: TableMarker> Chunk<M> for &'a IdList<M> {
+    type Whole = Self;
+    unsafe fn chunk(whole: &mut Self, _range: IdRange<'static, Id<M>>) -> Self {
+        *whole
+    }
+}
+macro_rules! impl_tuple_chunk {
+    ($($A:ident),*) => {
+        #[allow(non_snake_case)]
+        unsafe impl<M: TableMarker, $($A: Chunk<M>),*> Chunk<M> for ($($A,)*) {
+            type Whole = ($($A::Whole,)*);
+            unsafe fn chunk(whole: &mut Self::Whole, range: IdRange<'static, Id<M>>) -> Self {
+                let ($($A,)*) = whole;
+                ($($A::chunk($A, range),)*)
+            }
+        }
+    };
+}
+impl_tuple_chunk!(A);
+impl_tuple_chunk!(A, B);
+impl_tuple_chunk!(A, B, C);
+impl_tuple_chunk!(A, B, C, D);
+impl_tuple_chunk!(A, B, C, D, E);
+
+/// A list of kernels to be run, with those that don't conflict running at the same time on
+/// their own threads. Two kernels conflict if they use the same resource and either writes to it.
+///
+/// Each call to `run` runs every kernel once, in waves. A kernel goes in the wave after the last
+/// one holding an earlier kernel that it conflicts with, so conflicting kernels still run in the
+/// order they were added.
+#[derive(Default)]
+pub struct Schedule {
+    kernels: Vec<Kernel>,
+}
+impl Schedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn push(&mut self, kernel: Kernel) {
+        self.kernels.push(kernel);
+    }
+    pub fn len(&self) -> usize { self.kernels.len() }
+    pub fn is_empty(&self) -> bool { self.kernels.is_empty() }
+    pub fn conflicts(a: &Kernel, b: &Kernel) -> bool {
+        a.resources().iter().any(|&(ty, acc)| {
+            b.resources().iter().any(|&(oty, oacc)| {
+                ty == oty && (acc == Access::Write || oacc == Access::Write)
+            })
+        })
+    }
+    /// The indices of the kernels in each wave.
+    pub fn waves(&self) -> Vec<Vec<usize>> {
+        let mut level = Vec::with_capacity(self.kernels.len());
+        let mut waves: Vec<Vec<usize>> = vec![];
+        for (i, kernel) in self.kernels.iter().enumerate() {
+            let l = (0..i)
+                .filter(|&j| Self::conflicts(kernel, &self.kernels[j]))
+                .map(|j| level[j] + 1)
+                .max()
+                .unwrap_or(0);
+            level.push(l);
+            if waves.len() <= l {
+                waves.push(vec![]);
+            }
+            waves[l].push(i);
+        }
+        waves
+    }
+    pub fn run(&mut self, universe: &Universe) {
+        for wave in self.waves() {
+            if let [i] = wave[..] {
+                universe.run(&mut self.kernels[i]);
+                continue;
+            }
+            let mut kernels: Vec<Option<&mut Kernel>> = self.kernels.iter_mut().map(Some).collect();
+            std::thread::scope(|scope| {
+                let threads: Vec<_> = wave
+                    .iter()
+                    .map(|&i| {
+                        let kernel = kernels[i].take().unwrap();
+                        scope.spawn(move || universe.run(kernel))
+                    })
+                    .collect();
+                // Joining them ourselves keeps the original panic message.
+                for thread in threads {
+                    if let Err(panic) = thread.join() {
+                        std::panic::resume_unwind(panic);
+                    }
+                }
+            });
+        }
+    }
+}
+impl fmt::Debug for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.kernels.iter().map(|k| &k.name))
+            .finish()
+    }
 }
 
 /// Implemented for certain closures.
@@ -243,19 +662,59 @@ impl Universe {
 /// If your closure isn't a `Kernel`, ensure that:
 /// 1. All arguments are `Extract`. (You can test this by writing `fn assert<T: Extract>() {}
 ///    assert::<T>();`)
-/// 2. You don't have an unreasonable number of arguments. (If necessary, you can group them up via `decl_context!`.)
+/// 2. You don't have more than 20 arguments. If necessary, you can group them up via
+///    `decl_context!`, or into tuples of up to 5, which may be nested:
+///    `|(a, b, c, d, e): (A, B, C, D, E), ((f, g), h): ((F, G), H), …|`.
 /// 3. The return value is appropriate. `Kernel` itself has no restrictions on the return type,
 ///    however:
 ///    - `kmap` requires the return value be `()`.
 ///    - `kmap_return` and `run_return` requires `AnyDebug`, which means it must be `'static`.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a kernel",
+    note = "kernels take at most 20 arguments, each of which must be `Extract`",
+    note = "group extra arguments into a `#[v9::context]` struct, or into (nested) tuples of up to 5",
+)]
 pub unsafe trait KernelFn<Dump, Ret>: EachResource<Dump, Ret> {
     unsafe fn run(&mut self, args: Rez, cleanup: &ResetBuffer) -> Ret;
 }
 
+/// Like `KernelFn`, but may only be run once.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a kernel",
+    note = "kernels take at most 20 arguments, each of which must be `Extract`",
+    note = "group extra arguments into a `#[v9::context]` struct, or into (nested) tuples of up to 5",
+)]
 pub unsafe trait KernelFnOnce<Dump, Ret>: EachResource<Dump, Ret> {
     unsafe fn run(self, args: Rez, cleanup: &ResetBuffer) -> Ret;
 }
 
+/// Implemented for closures that return a `Future`; see `Universe::eval_async`.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not an async kernel",
+    note = "kernels take at most 20 arguments, each of which must be `Extract`",
+    note = "group extra arguments into a `#[v9::context]` struct, or into (nested) tuples of up to 5",
+)]
+pub unsafe trait KernelFnAsync<Dump, Fut: Future>: EachResource<Dump, Fut> {
+    unsafe fn run(self, args: Rez, cleanup: &ResetBuffer) -> Fut::Output;
+}
+
+/// Runs a future to completion on the current thread.
+fn block_on<F: Future>(fut: F) -> F::Output {
+    struct Unpark(std::thread::Thread);
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) { self.0.unpark() }
+    }
+    let waker = Waker::from(Arc::new(Unpark(std::thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut fut = Box::pin(fut);
+    loop {
+        match fut.as_mut().poll(&mut cx) {
+            Poll::Ready(ret) => return ret,
+            Poll::Pending => std::thread::park(),
+        }
+    }
+}
+
 pub unsafe trait EachResource<Dump, Ret> {
     // FIXME: It'd be nice to give a return value. However we can't because `Kernel` is dynamic.
     // FIXME: What if we passed in `&mut AnyDebug=Option<R>`?
@@ -305,7 +764,7 @@ impl LockBuffer {
     {
         Self::new0(K::each_resource)
     }
-    fn new0(each_resource: fn(&mut dyn FnMut(Ty, Access))) -> Self {
+    fn new0(each_resource: impl FnOnce(&mut dyn FnMut(Ty, Access))) -> Self {
         let mut resources = vec![];
         let mut write = HashSet::new();
         let mut any = HashSet::new();
@@ -370,6 +829,23 @@ impl Kernel {
             name,
         }
     }
+    /// Like `new`, but with a readable name to use in panic messages instead of the closure's
+    /// type name.
+    #[track_caller]
+    pub fn named<Dump, Ret, K>(name: impl Into<Cow<'static, str>>, k: K) -> Self
+    where
+        Ret: StdAny,
+        K: KernelFn<Dump, Ret>,
+        K: 'static + Send + Sync,
+        Dump: Send + Sync,
+    {
+        let mut kernel = Self::new(k);
+        kernel.set_name(name);
+        kernel
+    }
+    pub fn set_name(&mut self, name: impl Into<Cow<'static, str>>) {
+        self.name.name = name.into();
+    }
     /// A kernel may have arguments that the `Universe` doesn't know about.
     /// Any such arguments must be at the front of the parameter list,
     /// and must be pushed in the same order as the parameters.
@@ -388,6 +864,13 @@ impl Kernel {
         PushArgs(Some(self))
     }
     pub fn resources(&self) -> &[(Ty, Access)] { &self.buffer.resources }
+    /// Like `resources`, but with readable names; see `pretty_ty`.
+    pub fn resource_names(&self) -> Vec<(String, Access)> {
+        self.resources().iter().map(|&(ty, acc)| (pretty_ty(ty), acc)).collect()
+    }
+    pub fn is_readonly(&self) -> bool {
+        self.resources().iter().all(|(_, acc)| *acc == Access::Read)
+    }
 }
 pub struct PushArgs<'a>(Option<&'a mut Kernel>);
 impl<'a> PushArgs<'a> {
@@ -519,6 +1002,27 @@ macro_rules! impl_kernel {
                 ret
             }
         }
+        #[allow(non_snake_case)]
+        unsafe impl<$($A,)* Fut, X> KernelFnAsync<($($A,)*), Fut> for X
+        where
+            X: FnOnce($($A),*) -> Fut,
+            Fut: Future,
+            $($A: Extract,)*
+        {
+            unsafe fn run(self, mut args: Rez, cleanup: &ResetBuffer) -> Fut::Output {
+                $(let mut $A: $A::Owned = $A::extract(cleanup.universe, &mut args);)*
+                let ret = {
+                    $(let $A: $A = $A::convert(cleanup.universe, &mut $A as *mut $A::Owned);)*
+                    // The future may hold onto the arguments, so it must be finished before
+                    // the locks are released.
+                    block_on(self($($A),*))
+                };
+                $(let $A: $A::Cleanup = $A::Cleanup::pre_cleanup($A, cleanup.universe);)*
+                let _post_cleanup = cleanup.cleanup(); // Releases the locks.
+                $($A.post_cleanup(cleanup.universe);)*
+                ret
+            }
+        }
         impl_kernel! { @ $($A),* }
     };
     (@ $_:ident) => {};
@@ -526,7 +1030,7 @@ macro_rules! impl_kernel {
         impl_kernel! { $($A),* }
     };
 }
-impl_kernel! { A14, A13, A12, A11, A10, A09, A08, A07, A06, A05, A04, A03, A02, A01, A00 }
+impl_kernel! { A19, A18, A17, A16, A15, A14, A13, A12, A11, A10, A09, A08, A07, A06, A05, A04, A03, A02, A01, A00 }
 unsafe impl<X, Ret> EachResource<(), Ret> for X
 where
     X: FnMut() -> Ret,
@@ -122,6 +122,9 @@ pub use v9_attr::*;
 
 #[doc(hidden)]
 pub extern crate paste;
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+pub extern crate serde as __serde;
 
 // FIXME: Use UniquePtr, etc...?
 // FIXME: Add universe.deny(Ty) to allow constraints like "table is not sparse"
@@ -145,17 +148,20 @@ pub mod prelude {
     pub use crate::object::{Universe, Register};
     pub use crate::table::TableMarker;
     pub use crate::id::Check as _;
+    pub use crate::property::SelectedEntity as _;
 }
 
 /// Provides a single import statement for `decl_table!`.
 pub mod prelude_macro {
-    pub use crate::column::{Column, EditColumn, ReadColumn, WriteColumn};
+    pub use crate::column::{Column, EditColumn, ReadColumn, SparseColumn, WriteColumn};
+    pub use crate::event::{Cleared, Move};
     pub use crate::extract::*;
-    pub use crate::id::{Check, CheckedIter, Id as IdV9, CheckedId as CheckedIdV9, IdList, IdRange, Raw, UncheckedIdRange};
-    pub use crate::linkage::ForeignKey;
+    pub use crate::id::{Check, CheckedIter, Id as IdV9, CheckedId as CheckedIdV9, IdList, IdRange, Raw, RunList, SelectedIter, UncheckedIdRange};
+    pub use crate::kernel::KernelArg;
+    pub use crate::linkage::{ColumnIndex, ForeignKey};
     pub use crate::object::{Universe, Register};
     pub use crate::property::*;
-    pub use crate::table::{ColumnHeader, TableHeader, TableMarker};
+    pub use crate::table::{ColumnHeader, TableHeader, TableMarker, TableRows};
     pub use ezty::Ty;
     pub use std::fmt;
 }
@@ -168,7 +174,7 @@ pub mod prelude_lib {
     pub use crate::object::*;
     pub use crate::prelude::*;
     pub use crate::property::*;
-    pub use crate::table::{TableHeader, TableMarker};
+    pub use crate::table::{TableHeader, TableMarker, TableRows, TableSnapshot};
     pub use crate::util::*;
     pub use crate::linkage::*;
     pub use ezty::{AnyDebug, Ty, type_name};
@@ -4,7 +4,10 @@ use crate::event::*;
 use crate::kernel::{Kernel, KernelArg, KernelFn};
 use crate::prelude_lib::*;
 use crate::id::IdRange;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 use ezty::{Ty, AnyDebug};
 use std::mem;
 
@@ -46,6 +49,52 @@ impl<M: TableMarker, T: AnyDebug + Ord + Clone> ColumnIndex<M, T> {
             .map(|((_, i), _)| *i)
     }
 }
+impl<M: TableMarker, T: AnyDebug + Ord + Clone> ColumnIndex<M, T> {
+    /// Throw away the index and recreate it from the column.
+    pub fn rebuild(&mut self, ids: &IdList<M>, col: &Column<M, T>) {
+        self.map.clear();
+        for id in ids {
+            self.map.insert((col.data[id.to_usize()].clone(), id.uncheck()), ());
+        }
+    }
+}
+impl<M: TableMarker, T: AnyDebug + Ord + Clone + Hash> ColumnIndex<M, T> {
+    /// A hash of the live contents of the column. This uses `DefaultHasher`, so it is only
+    /// meaningful to the same build of the program.
+    pub fn checksum(ids: &IdList<M>, col: &Column<M, T>) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for id in ids {
+            id.to_usize().hash(&mut hasher);
+            col.data[id.to_usize()].hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+    /// Save the index, along with a checksum of the column it was built from.
+    pub fn save(&self, ids: &IdList<M>, col: &Column<M, T>) -> SavedIndex<M, T> {
+        SavedIndex {
+            checksum: Self::checksum(ids, col),
+            keys: self.map.keys().cloned().collect(),
+        }
+    }
+    /// Load a saved index. If it doesn't match the column, it is rebuilt instead.
+    /// Returns `false` if it had to be rebuilt.
+    pub fn load(&mut self, saved: SavedIndex<M, T>, ids: &IdList<M>, col: &Column<M, T>) -> bool {
+        if saved.checksum != Self::checksum(ids, col) {
+            self.rebuild(ids, col);
+            return false;
+        }
+        self.map = saved.keys.into_iter().map(|k| (k, ())).collect();
+        true
+    }
+}
+/// A `ColumnIndex`, saved by `ColumnIndex::save`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound = "T: serde::Serialize + serde::de::DeserializeOwned"))]
+pub struct SavedIndex<M: TableMarker, T> {
+    pub checksum: u64,
+    pub keys: Vec<(T, Id<M>)>,
+}
 impl<M: TableMarker, T: AnyDebug + Ord> Default for ColumnIndex<M, T> {
     fn default() -> Self {
         ColumnIndex {
@@ -79,6 +128,266 @@ unsafe impl<'a, M: TableMarker, T: AnyDebug + Ord> Extract for &'a mut ColumnInd
     }
     type Cleanup = ();
 }
+
+/// The rows of `L` that point at a given row of `F`. This is the `ColumnIndex<L, Id<F>>` that is
+/// kept for every foreign key, under a name that is easier to spell.
+///
+/// Declared foreign keys get their index automatically. A key from a table to itself doesn't, so
+/// call `Universe::add_reverse_lookup::<L, L>()` for those.
+#[derive(Debug)]
+pub struct ForeignIndex<'a, L: TableMarker, F: TableMarker> {
+    pub index: &'a ColumnIndex<L, Id<F>>,
+}
+impl<'a, L: TableMarker, F: TableMarker> ForeignIndex<'a, L, F> {
+    /// The rows of `L` whose key is `parent`.
+    pub fn children(&self, parent: Id<F>) -> impl DoubleEndedIterator<Item=Id<L>> + Clone + fmt::Debug + 'a {
+        self.index.find(parent)
+    }
+    pub fn has_children(&self, parent: Id<F>) -> bool {
+        self.children(parent).next().is_some()
+    }
+}
+unsafe impl<'a, L: TableMarker, F: TableMarker> ExtractOwned for ForeignIndex<'a, L, F> {
+    type Ty = ColumnIndex<L, Id<F>>;
+    const ACC: Access = Access::Read;
+    unsafe fn extract(_universe: &Universe, rez: &mut Rez) -> Self {
+        ForeignIndex {
+            index: rez.take_ref_downcast(),
+        }
+    }
+}
+
+/// A column, read-only, together with its `ColumnIndex`; for looking rows up by value without
+/// spelling out both types. (The index must have been added with `Universe::add_index`.)
+#[derive(Debug)]
+pub struct IndexedColumn<'a, M: TableMarker, T: AnyDebug + Ord> {
+    pub col: &'a Column<M, T>,
+    pub index: &'a ColumnIndex<M, T>,
+}
+impl<'a, M: TableMarker, T: AnyDebug + Ord + Clone> IndexedColumn<'a, M, T> {
+    /// The rows whose value is `t`, with their values.
+    pub fn find(&self, t: T) -> impl DoubleEndedIterator<Item=(Id<M>, &'a T)> + 'a {
+        let col = self.col;
+        self.index
+            .find(t)
+            .map(move |id| (id, &col.data()[id.to_usize()]))
+    }
+}
+unsafe impl<'a, M: TableMarker, T: AnyDebug + Ord> Extract for IndexedColumn<'a, M, T> {
+    fn each_resource(f: &mut dyn FnMut(Ty, Access)) {
+        f(Ty::of::<Column<M, T>>(), Access::Read);
+        f(Ty::of::<ColumnIndex<M, T>>(), Access::Read);
+    }
+    type Owned = Option<Self>;
+    unsafe fn extract(_universe: &Universe, rez: &mut Rez) -> Self::Owned {
+        Some(IndexedColumn {
+            col: rez.take_ref_downcast(),
+            index: rez.take_ref_downcast(),
+        })
+    }
+    unsafe fn convert(_universe: &Universe, owned: *mut Self::Owned) -> Self {
+        (*owned).take().unwrap()
+    }
+    type Cleanup = ();
+}
+
+/// Like `ColumnIndex`, but indexes a key computed from the column's value.
+/// See `Universe::add_computed_index`.
+#[derive(Debug)]
+pub struct ComputedIndex<M: TableMarker, T: AnyDebug, K: AnyDebug + Ord> {
+    pub map: BTreeMap<(K, Id<M>), ()>,
+    _t: PhantomData<fn() -> T>,
+}
+impl<M: TableMarker, T: AnyDebug, K: AnyDebug + Ord + Clone> ComputedIndex<M, T, K> {
+    pub fn find<'a>(&'a self, k: K) -> impl DoubleEndedIterator<Item=Id<M>> + Clone + fmt::Debug + 'a {
+        self.map
+            .range((k.clone(), Id(M::RawId::ZERO))..(k, Id(M::RawId::LAST)))
+            .map(|((_, i), _)| *i)
+    }
+    pub fn range<'a>(&'a self, low: K, high: K) -> impl DoubleEndedIterator<Item=Id<M>> + Clone + fmt::Debug + 'a {
+        self.map
+            .range((low, Id(M::RawId::ZERO))..(high, Id(M::RawId::LAST)))
+            .map(|((_, i), _)| *i)
+    }
+}
+impl<M: TableMarker, T: AnyDebug, K: AnyDebug + Ord> Default for ComputedIndex<M, T, K> {
+    fn default() -> Self {
+        ComputedIndex {
+            map: BTreeMap::new(),
+            _t: PhantomData,
+        }
+    }
+}
+unsafe impl<'a, M: TableMarker, T: AnyDebug, K: AnyDebug + Ord> Extract for &'a ComputedIndex<M, T, K> {
+    fn each_resource(f: &mut dyn FnMut(Ty, Access)) {
+        f(Ty::of::<ComputedIndex<M, T, K>>(), Access::Read)
+    }
+    type Owned = Self;
+    unsafe fn extract(_universe: &Universe, rez: &mut Rez) -> Self::Owned {
+        rez.take_ref_downcast()
+    }
+    unsafe fn convert(_universe: &Universe, owned: *mut Self::Owned) -> Self {
+        *owned
+    }
+    type Cleanup = ();
+}
+unsafe impl<'a, M: TableMarker, T: AnyDebug, K: AnyDebug + Ord> Extract for &'a mut ComputedIndex<M, T, K> {
+    fn each_resource(f: &mut dyn FnMut(Ty, Access)) {
+        f(Ty::of::<ComputedIndex<M, T, K>>(), Access::Write)
+    }
+    type Owned = Self;
+    unsafe fn extract(_universe: &Universe, rez: &mut Rez) -> Self::Owned {
+        rez.take_mut_downcast()
+    }
+    unsafe fn convert(_universe: &Universe, owned: *mut Self::Owned) -> Self {
+        *owned
+    }
+    type Cleanup = ();
+}
+
+/// The rows that have been pushed, edited, or removed since the log was last cleared; an
+/// automatic dirty-set. Reads aren't logged. Enable with `my_table::track_touches(universe)`.
+///
+/// This makes every `EditColumn` on the table log its changes, so it isn't free.
+#[derive(Debug, Default)]
+pub struct TouchLog<M: TableMarker> {
+    pub touched: RunList<M>,
+}
+impl<M: TableMarker> TouchLog<M> {
+    pub fn touch(&mut self, id: Id<M>) {
+        if !self.touched.contains(id) {
+            self.touched.push(id);
+        }
+    }
+    /// Take the touched ids, leaving the log empty.
+    pub fn take(&mut self) -> RunList<M> {
+        mem::take(&mut self.touched)
+    }
+    pub fn clear(&mut self) { self.touched.clear(); }
+}
+unsafe impl<'a, M: TableMarker> Extract for &'a TouchLog<M> {
+    fn each_resource(f: &mut dyn FnMut(Ty, Access)) {
+        f(Ty::of::<TouchLog<M>>(), Access::Read)
+    }
+    type Owned = Self;
+    unsafe fn extract(_universe: &Universe, rez: &mut Rez) -> Self::Owned {
+        rez.take_ref_downcast()
+    }
+    unsafe fn convert(_universe: &Universe, owned: *mut Self::Owned) -> Self {
+        *owned
+    }
+    type Cleanup = ();
+}
+unsafe impl<'a, M: TableMarker> Extract for &'a mut TouchLog<M> {
+    fn each_resource(f: &mut dyn FnMut(Ty, Access)) {
+        f(Ty::of::<TouchLog<M>>(), Access::Write)
+    }
+    type Owned = Self;
+    unsafe fn extract(_universe: &Universe, rez: &mut Rez) -> Self::Owned {
+        rez.take_mut_downcast()
+    }
+    unsafe fn convert(_universe: &Universe, owned: *mut Self::Owned) -> Self {
+        *owned
+    }
+    type Cleanup = ();
+}
+
+/// The edits made to a column, oldest first, kept across kernels until drained; for undo.
+/// Each entry is an id and the value it had *before* the edit. Once there are `capacity`
+/// entries, the oldest are dropped. Enable with `Universe::add_edit_log::<M, T>(capacity)`.
+///
+/// Like `TouchLog`, this makes every `EditColumn` on the column log its changes.
+#[derive(Debug)]
+pub struct EditLog<M: TableMarker, T> {
+    pub edits: VecDeque<(Id<M>, T)>,
+    pub capacity: usize,
+}
+impl<M: TableMarker, T> EditLog<M, T> {
+    pub fn new(capacity: usize) -> Self {
+        EditLog {
+            edits: VecDeque::new(),
+            capacity,
+        }
+    }
+    pub fn record(&mut self, id: Id<M>, old: T) {
+        if self.capacity == 0 { return; }
+        if self.edits.len() == self.capacity {
+            self.edits.pop_front();
+        }
+        self.edits.push_back((id, old));
+    }
+    /// Take the edits, oldest first, leaving the log empty.
+    pub fn drain(&mut self) -> Vec<(Id<M>, T)> {
+        self.edits.drain(..).collect()
+    }
+}
+unsafe impl<'a, M: TableMarker, T: AnyDebug> Extract for &'a EditLog<M, T> {
+    fn each_resource(f: &mut dyn FnMut(Ty, Access)) {
+        f(Ty::of::<EditLog<M, T>>(), Access::Read)
+    }
+    type Owned = Self;
+    unsafe fn extract(_universe: &Universe, rez: &mut Rez) -> Self::Owned {
+        rez.take_ref_downcast()
+    }
+    unsafe fn convert(_universe: &Universe, owned: *mut Self::Owned) -> Self {
+        *owned
+    }
+    type Cleanup = ();
+}
+unsafe impl<'a, M: TableMarker, T: AnyDebug> Extract for &'a mut EditLog<M, T> {
+    fn each_resource(f: &mut dyn FnMut(Ty, Access)) {
+        f(Ty::of::<EditLog<M, T>>(), Access::Write)
+    }
+    type Owned = Self;
+    unsafe fn extract(_universe: &Universe, rez: &mut Rez) -> Self::Owned {
+        rez.take_mut_downcast()
+    }
+    unsafe fn convert(_universe: &Universe, owned: *mut Self::Owned) -> Self {
+        *owned
+    }
+    type Cleanup = ();
+}
+
+/// The most rows a table may have; see `Universe::set_capacity_limit`. `live` is kept up to date
+/// by trackers, as they can't look at the `IdList` while it's being flushed.
+#[derive(Debug)]
+pub struct CapacityLimit<M: TableMarker> {
+    pub max: usize,
+    pub live: usize,
+    _m: PhantomData<M>,
+}
+unsafe impl<M: TableMarker> Property for CapacityLimit<M> {}
+
+/// Memoized values for each distinct `K` in a column of `M`, eg an aggregate over each group of
+/// rows. Values are computed on demand by `Universe::derived`, and forgotten when a row with that
+/// key is pushed, deleted, or has its key edited. See `Universe::add_derived_index`.
+pub struct DerivedIndex<M: TableMarker, K: AnyDebug, V: AnyDebug> {
+    pub cache: HashMap<K, V>,
+    /// Bumped on every invalidation, so that a value computed meanwhile isn't cached.
+    generation: u64,
+    recompute: Arc<dyn Fn(&Universe, &K) -> V + Send + Sync>,
+    _m: PhantomData<M>,
+}
+impl<M: TableMarker, K: AnyDebug + Eq + Hash, V: AnyDebug> DerivedIndex<M, K, V> {
+    pub fn invalidate(&mut self, k: &K) {
+        self.cache.remove(k);
+        self.generation += 1;
+    }
+    pub fn invalidate_all(&mut self) {
+        self.cache.clear();
+        self.generation += 1;
+    }
+}
+impl<M: TableMarker, K: AnyDebug, V: AnyDebug> fmt::Debug for DerivedIndex<M, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DerivedIndex")
+            .field("cache", &self.cache)
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+unsafe impl<M: TableMarker, K: AnyDebug, V: AnyDebug> Property for DerivedIndex<M, K, V> {}
+
 impl Universe {
     pub fn add_index<M: TableMarker, T>(&mut self)
     where
@@ -109,9 +418,7 @@ impl Universe {
                 // 3. Edit
                 // col[i] = new;
                 // index[(old, i)] -> index[(new, i)]
-                let col = ReadColumn { col: ev.col() };
-                for &(id, new) in &ev.new {
-                    let old = col[id];
+                for (id, &old, &new) in ev.iter_changes() {
                     // if old == new { continue; }
                     // We could do this check.
                     // But it'd slow down well-written code.
@@ -131,9 +438,14 @@ impl Universe {
                 }
             },
         );
-        #[cfg(feature = "move_event")]
-        self.add_tracker_with_ref_arg::<_, _, Moved<M>>(
-            |ev: KernelArg<&Moved<M>>, index: &mut ColumnIndex<M, T>, local: ReadColumn<M, T>| {
+        self.add_tracker_with_ref_arg::<_, _, Cleared<M>>(
+            |_ev: KernelArg<&Cleared<M>>, index: &mut ColumnIndex<M, T>| {
+                // 4b. Clear
+                index.map.clear();
+            },
+        );
+        self.add_tracker_with_ref_arg::<_, _, Move<M>>(
+            |ev: KernelArg<&Move<M>>, index: &mut ColumnIndex<M, T>, local: ReadColumn<M, T>| {
                 // 5. Moved
                 // col[i] -> col[j];
                 // del index[(val, i)];
@@ -146,6 +458,344 @@ impl Universe {
             },
         );
     }
+    /// Index the `Id<F>` column of `L`, so that it can be read as a `ForeignIndex<L, F>`.
+    /// This is already done for declared foreign keys, except those from a table to itself.
+    pub fn add_reverse_lookup<L: TableMarker, F: TableMarker>(&mut self) {
+        self.add_index::<L, Id<F>>();
+    }
+    /// Index a table by a key derived from one of its columns, eg `|pos: &Pos| pos.x as i32 / 16`.
+    /// The index is a `ComputedIndex<M, T, K>`; to have two on one column, use different types
+    /// for `K`.
+    ///
+    /// `key` must be pure. It is called again on the old value when a row is edited or deleted,
+    /// and must give back the same key that was indexed.
+    pub fn add_computed_index<M, T, K, F>(&mut self, key: F)
+    where
+        M: TableMarker,
+        T: AnyDebug,
+        K: AnyDebug + Ord,
+        F: Fn(&T) -> K + 'static + Send + Sync,
+    {
+        self.add_mut(
+            Ty::of::<ComputedIndex<M, T, K>>(),
+            ComputedIndex::<M, T, K>::default(),
+        );
+        let key = Arc::new(key);
+        let k = key.clone();
+        self.add_tracker_with_ref_arg::<_, _, Push<M, lifestage::MEMORY>>(
+            move |ev: KernelArg<&Push<M, lifestage::MEMORY>>, index: &mut ComputedIndex<M, T, K>, local: ReadColumn<M, T>| {
+                for id in &ev.ids {
+                    index.map.insert(((*k)(&local[id]), id), ());
+                }
+            },
+        );
+        let k = key.clone();
+        self.add_tracker_with_ref_arg::<_, _, Edit<M, T>>(
+            move |ev: KernelArg<&Edit<M, T>>, index: &mut ComputedIndex<M, T, K>| {
+                for (id, old, new) in ev.iter_changes() {
+                    index.map.remove(&((*k)(old), id));
+                    index.map.insert(((*k)(new), id), ());
+                }
+            },
+        );
+        self.add_tracker_with_ref_arg::<_, _, Delete<M, lifestage::MEMORY>>(
+            move |ev: KernelArg<&Delete<M, lifestage::MEMORY>>, index: &mut ComputedIndex<M, T, K>, col: ReadColumn<M, T>| {
+                for id in &ev.ids {
+                    index.map.remove(&((*key)(&col[id]), id));
+                }
+            },
+        );
+        self.add_tracker_with_ref_arg::<_, _, Cleared<M>>(
+            |_ev: KernelArg<&Cleared<M>>, index: &mut ComputedIndex<M, T, K>| {
+                index.map.clear();
+            },
+        );
+    }
+    /// Copy each row deleted from `M` into the archive table `A`.
+    ///
+    /// This runs on the `LOGICAL` `Delete` event. The `IdList` has already forgotten the ids at
+    /// that point, but nothing can overwrite the columns until it's unlocked and the ids get
+    /// recycled, so the old values are still there. (Dense tables are compacted afterwards, too.)
+    pub fn archive_deletes<M, A>(&mut self)
+    where
+        M: TableRows,
+        A: TableRows,
+        A::Row: From<M::Row>,
+    {
+        assert_ne!(Ty::of::<M>(), Ty::of::<A>(), "table {} can't be its own archive", M::NAME);
+        self.add_tracker(|universe: &Universe, ev: &mut Delete<M, lifestage::LOGICAL>| {
+            let rows = M::clone_rows(universe, &ev.ids);
+            A::push_rows(universe, rows.into_iter().map(Into::into).collect());
+        });
+    }
+    /// Adds a `Column<M, T>` to a table from outside of its `decl_table!`, eg from a plugin. Rows
+    /// pushed through the table's `Write` get a clone of `default`, so that the column stays as
+    /// long as the table's own. Existing rows get it too. Read and edit it as a `ReadColumn<M, T>`
+    /// or `EditColumn<M, T>`.
+    pub fn add_column_with_default<M: TableMarker, T: AnyDebug + Clone>(&mut self, default: T) {
+        let mut col = Column::<M, T>::new();
+        if self.has::<IdList<M>>() {
+            let len = self.with(|ids: &IdList<M>| ids.outer_capacity());
+            col.data.resize(len, default.clone());
+        }
+        self.add_mut(Ty::of::<Column<M, T>>(), col);
+        self.add_tracker_with_ref_arg::<_, _, Push<M, lifestage::MEMORY>>(
+            move |ev: KernelArg<&Push<M, lifestage::MEMORY>>, mut col: WriteColumn<M, T>| {
+                let data = unsafe { col.col.get_mut().data_mut() };
+                for id in ev.ids.iter() {
+                    let i = id.to_usize();
+                    if i < data.len() {
+                        data[i] = default.clone();
+                    } else {
+                        data.resize(i + 1, default.clone());
+                    }
+                }
+            },
+        );
+        self.add_tracker_with_ref_arg::<_, _, Cleared<M>>(
+            |_ev: KernelArg<&Cleared<M>>, mut col: WriteColumn<M, T>| {
+                unsafe { col.col.get_mut().data_mut().clear(); }
+            },
+        );
+        self.add_tracker_with_ref_arg::<_, _, Move<M>>(
+            |ev: KernelArg<&Move<M>>, mut col: WriteColumn<M, T>, ids: &IdList<M>| {
+                // The same as the table's own columns.
+                let data = unsafe { col.col.get_mut().data_mut() };
+                for &(old, new) in &ev.ids {
+                    data.swap(old.to_usize(), new.to_usize());
+                }
+                data.truncate(ids.outer_capacity());
+            },
+        );
+    }
+    /// Adds a `SparseColumn<M, T>`. Entries are dropped when their row is removed, and follow it
+    /// when it is moved.
+    pub fn add_sparse_column<M: TableMarker, T: AnyDebug>(&mut self) {
+        self.add_mut(
+            Ty::of::<SparseColumn<M, T>>(),
+            SparseColumn::<M, T>::new(),
+        );
+        self.add_tracker_with_ref_arg::<_, _, Delete<M, lifestage::MEMORY>>(
+            |ev: KernelArg<&Delete<M, lifestage::MEMORY>>, col: &mut SparseColumn<M, T>| {
+                if col.is_empty() { return; }
+                for id in &ev.ids {
+                    col.remove(id);
+                }
+            },
+        );
+        self.add_tracker_with_ref_arg::<_, _, Cleared<M>>(
+            |_ev: KernelArg<&Cleared<M>>, col: &mut SparseColumn<M, T>| {
+                *col = SparseColumn::new();
+            },
+        );
+        self.add_tracker_with_ref_arg::<_, _, Move<M>>(
+            |ev: KernelArg<&Move<M>>, col: &mut SparseColumn<M, T>| {
+                // The same swap that the table's columns get.
+                for &(old, new) in &ev.ids {
+                    let a = col.remove(old);
+                    let b = col.remove(new);
+                    if let Some(a) = a { col.insert(new, a); }
+                    if let Some(b) = b { col.insert(old, b); }
+                }
+            },
+        );
+    }
+    /// Adds a `TouchLog<M>`, and logs pushes & removals to it. Edits are logged per-column by
+    /// `add_touch_log_column`. You probably want the `track_touches` generated by `decl_table!`,
+    /// which does both.
+    pub fn add_touch_log<M: TableMarker>(&mut self) {
+        self.add_mut(
+            Ty::of::<TouchLog<M>>(),
+            TouchLog::<M>::default(),
+        );
+        self.add_tracker_with_ref_arg::<_, _, Push<M, lifestage::MEMORY>>(
+            |ev: KernelArg<&Push<M, lifestage::MEMORY>>, log: &mut TouchLog<M>| {
+                for id in &ev.ids {
+                    log.touch(id);
+                }
+            },
+        );
+        self.add_tracker_with_ref_arg::<_, _, Delete<M, lifestage::MEMORY>>(
+            |ev: KernelArg<&Delete<M, lifestage::MEMORY>>, log: &mut TouchLog<M>| {
+                for id in &ev.ids {
+                    log.touch(id);
+                }
+            },
+        );
+        self.add_tracker_with_ref_arg::<_, _, Cleared<M>>(
+            |ev: KernelArg<&Cleared<M>>, log: &mut TouchLog<M>| {
+                log.touched.merge(&ev.ids);
+            },
+        );
+    }
+    pub fn add_touch_log_column<M: TableMarker, T>(&mut self)
+    where
+        T: AnyDebug + Clone + Send + Sync,
+    {
+        self.add_tracker_with_ref_arg::<_, _, Edit<M, T>>(
+            |ev: KernelArg<&Edit<M, T>>, log: &mut TouchLog<M>| {
+                for &(id, _) in &ev.new {
+                    log.touch(id);
+                }
+            },
+        );
+    }
+    /// Start keeping an `EditLog` of the last `capacity` edits to the `T` column of `M`.
+    pub fn add_edit_log<M: TableMarker, T>(&mut self, capacity: usize)
+    where
+        T: AnyDebug + Clone + Send + Sync,
+    {
+        self.add_mut(
+            Ty::of::<EditLog<M, T>>(),
+            EditLog::<M, T>::new(capacity),
+        );
+        self.add_tracker_with_ref_arg::<_, _, Edit<M, T>>(
+            |ev: KernelArg<&Edit<M, T>>, log: &mut EditLog<M, T>| {
+                for (id, old, _new) in ev.iter_changes() {
+                    log.record(id, old.clone());
+                }
+            },
+        );
+    }
+    /// Take everything in the `EditLog<M, T>`, oldest first.
+    pub fn drain_edits<M: TableMarker, T: AnyDebug>(&self) -> Vec<(Id<M>, T)> {
+        self.with_mut(|log: &mut EditLog<M, T>| log.drain())
+    }
+    /// Panic if table `M` ever has more than `max` rows, to catch runaway growth. The panic
+    /// happens while the pushes are being flushed, so the rows do get pushed. Calling this again
+    /// changes the limit.
+    pub fn set_capacity_limit<M: TableMarker>(&mut self, max: usize) {
+        if self.has::<CapacityLimit<M>>() {
+            self.with_mut(|limit: &mut CapacityLimit<M>| limit.max = max);
+            return;
+        }
+        let live = if self.has::<IdList<M>>() {
+            self.with(|ids: &IdList<M>| ids.len())
+        } else {
+            0
+        };
+        self.add_mut(
+            Ty::of::<CapacityLimit<M>>(),
+            CapacityLimit::<M> { max, live, _m: PhantomData },
+        );
+        self.add_tracker_with_ref_arg::<_, _, Push<M, lifestage::MEMORY>>(
+            |ev: KernelArg<&Push<M, lifestage::MEMORY>>, limit: &mut CapacityLimit<M>| {
+                limit.live += ev.ids.len();
+                if limit.live > limit.max {
+                    panic!("table {} has {} rows, which is over its limit of {}", M::NAME, limit.live, limit.max);
+                }
+            },
+        );
+        self.add_tracker_with_ref_arg::<_, _, Delete<M, lifestage::MEMORY>>(
+            |ev: KernelArg<&Delete<M, lifestage::MEMORY>>, limit: &mut CapacityLimit<M>| {
+                limit.live = limit.live.saturating_sub(ev.ids.len());
+            },
+        );
+        self.add_tracker_with_ref_arg::<_, _, Cleared<M>>(
+            |ev: KernelArg<&Cleared<M>>, limit: &mut CapacityLimit<M>| {
+                limit.live = limit.live.saturating_sub(ev.ids.len());
+            },
+        );
+    }
+    /// Submit a hand-made `Selection` as an event of its own, for trackers of `Selection`. Unlike
+    /// `Select`, nothing follows foreign keys; the selection is returned as the trackers left it.
+    pub fn select(&self, mut selection: Selection) -> Selection {
+        self.submit_event(&mut selection);
+        selection
+    }
+    /// Cache the results of `recompute` for each key in the `K` column of `M`, as a
+    /// `DerivedIndex<M, K, V>`. Read them with `Universe::derived`. Changes to the `K` column
+    /// invalidate the affected keys; if `V` also depends on other columns, list them with
+    /// `add_derived_dependency`.
+    ///
+    /// `recompute` is run outside of any kernel, so it may `eval` whatever it needs.
+    pub fn add_derived_index<M, K, V>(&mut self, recompute: impl Fn(&Universe, &K) -> V + 'static + Send + Sync)
+    where
+        M: TableMarker,
+        K: AnyDebug + Eq + Hash,
+        V: AnyDebug,
+    {
+        self.add_mut(
+            Ty::of::<DerivedIndex<M, K, V>>(),
+            DerivedIndex::<M, K, V> {
+                cache: HashMap::new(),
+                generation: 0,
+                recompute: Arc::new(recompute),
+                _m: PhantomData,
+            },
+        );
+        self.add_tracker_with_ref_arg::<_, _, Push<M, lifestage::MEMORY>>(
+            |ev: KernelArg<&Push<M, lifestage::MEMORY>>, index: &mut DerivedIndex<M, K, V>, keys: ReadColumn<M, K>| {
+                for id in &ev.ids {
+                    index.invalidate(&keys[id]);
+                }
+            },
+        );
+        self.add_tracker_with_ref_arg::<_, _, Edit<M, K>>(
+            |ev: KernelArg<&Edit<M, K>>, index: &mut DerivedIndex<M, K, V>| {
+                for (_id, old, new) in ev.iter_changes() {
+                    index.invalidate(old);
+                    index.invalidate(new);
+                }
+            },
+        );
+        self.add_tracker_with_ref_arg::<_, _, Delete<M, lifestage::MEMORY>>(
+            |ev: KernelArg<&Delete<M, lifestage::MEMORY>>, index: &mut DerivedIndex<M, K, V>, keys: ReadColumn<M, K>| {
+                for id in &ev.ids {
+                    index.invalidate(&keys[id]);
+                }
+            },
+        );
+        self.add_tracker_with_ref_arg::<_, _, Cleared<M>>(
+            |_ev: KernelArg<&Cleared<M>>, index: &mut DerivedIndex<M, K, V>| {
+                index.invalidate_all();
+            },
+        );
+    }
+    /// Make edits to the `T` column of `M` invalidate the edited rows' keys in a
+    /// `DerivedIndex<M, K, V>`.
+    pub fn add_derived_dependency<M, K, V, T>(&mut self)
+    where
+        M: TableMarker,
+        K: AnyDebug + Eq + Hash,
+        V: AnyDebug,
+        T: AnyDebug,
+    {
+        self.add_tracker_with_ref_arg::<_, _, Edit<M, T>>(
+            |ev: KernelArg<&Edit<M, T>>, index: &mut DerivedIndex<M, K, V>, keys: ReadColumn<M, K>| {
+                for (id, _old, _new) in ev.iter_changes() {
+                    index.invalidate(&keys[id]);
+                }
+            },
+        );
+    }
+    /// The value of a `DerivedIndex<M, K, V>` for `k`, computing it if it isn't cached. This must
+    /// be called outside of kernels.
+    pub fn derived<M, K, V>(&self, k: &K) -> V
+    where
+        M: TableMarker,
+        K: AnyDebug + Eq + Hash + Clone,
+        V: AnyDebug + Clone,
+    {
+        let (recompute, generation) = match self.with(|index: &DerivedIndex<M, K, V>| {
+            index.cache
+                .get(k)
+                .cloned()
+                .ok_or_else(|| (index.recompute.clone(), index.generation))
+        }) {
+            Ok(v) => return v,
+            Err(miss) => miss,
+        };
+        let v = (*recompute)(self, k);
+        self.with_mut(|index: &mut DerivedIndex<M, K, V>| {
+            if index.generation == generation {
+                index.cache.insert(k.clone(), v.clone());
+            }
+        });
+        v
+    }
+    /// Add a kernel as a handler for `E`; the kernel receives the event as a `KernelArg<&E>`.
+    /// See [`Universe::add_tracker`] for what the kernel may lock.
     #[track_caller]
     pub fn add_tracker_with_ref_arg<F, Dump, E>(&mut self, f: F)
     where
@@ -186,6 +836,7 @@ impl Universe {
 pub trait ForeignKey {
     fn __v9_link_foreign_table_name() -> Option<Name> { None }
     fn __v9_link_foreign_key<LM: TableMarker>(_universe: &mut Universe) {}
+    fn __v9_restrict_foreign_key<LM: TableMarker>(_universe: &mut Universe) {}
 }
 impl<X> ForeignKey for X {}
 impl<FM: TableMarker> Id<FM> {
@@ -214,17 +865,32 @@ impl<FM: TableMarker> Id<FM> {
                 );
             },
         );
-        #[cfg(feature = "move_event")]
-        universe.add_tracker_with_ref_arg::<_, _, Moved<FM>>(
-            |ev: KernelArg<&Moved<FM>>, index: &ColumnIndex<LM, Self>, mut col: EditColumn<LM, Self>| {
+        universe.add_tracker_with_ref_arg::<_, _, Cleared<FM>>(
+            |ev: KernelArg<&Cleared<FM>>, list: &mut IdList<LM>, index: &ColumnIndex<LM, Self>| {
+                // `clear_fast` skips the `Delete`s, so cascade here instead.
+                list.delete_extend(
+                    ev.ids
+                        .iter()
+                        .flat_map(|fid| index.find(fid))
+                );
+            },
+        );
+        universe.add_tracker_with_ref_arg::<_, _, Move<FM>>(
+            |ev: KernelArg<&Move<FM>>, index: &ColumnIndex<LM, Self>, mut col: EditColumn<LM, Self>| {
                 // 7. Use the index to update everyone point at moved things.
                 // The index also needs to be updated.
                 // It'll take care of itself after the kernel finishes.
+                let mut edits = vec![];
                 for &(ofid, nfid) in &ev.ids {
                     for (&(_, id), ()) in index.map.range(ColumnIndex::full_range(ofid)) {
-                        col[id] = nfid;
+                        edits.push((id, nfid));
                     }
                 }
+                // EditColumn must be written in order.
+                edits.sort();
+                for (id, nfid) in edits {
+                    col[id] = nfid;
+                }
             },
         );
         universe.add_tracker_with_mut_arg::<_, _, Select<FM>>(
@@ -253,6 +919,22 @@ impl<FM: TableMarker> Id<FM> {
             },
         );
     }
+    /// Used by `#[on_delete(restrict)]`. Panics if a foreign row is deleted while a local row
+    /// still refers to it.
+    pub fn __v9_restrict_foreign_key<LM: TableMarker>(universe: &mut Universe) {
+        if Ty::of::<LM>() == Ty::of::<FM>() {
+            return;
+        }
+        universe.add_tracker_with_ref_arg::<_, _, Delete<FM, lifestage::LOGICAL>>(
+            |ev: KernelArg<&Delete<FM, lifestage::LOGICAL>>, index: &ColumnIndex<LM, Self>| {
+                for fid in ev.ids.iter() {
+                    if let Some(lid) = index.find(fid).next() {
+                        panic!("can't delete {:?}: it is still referred to by {:?}", fid, lid);
+                    }
+                }
+            },
+        );
+    }
 }
 impl<FM: TableMarker> IdRange<'static, Id<FM>> {
     pub fn __v9_link_foreign_table_name() -> Option<Name> {
@@ -351,6 +1033,30 @@ impl<FM: TableMarker> IdRange<'static, Id<FM>> {
             },
         );
     }
+    /// Used by `#[on_delete(restrict)]`. Panics if a foreign row is deleted while a local range
+    /// still covers it.
+    pub fn __v9_restrict_foreign_key<LM: TableMarker>(universe: &mut Universe) {
+        if Ty::of::<LM>() == Ty::of::<FM>() {
+            return;
+        }
+        universe.add_tracker_with_ref_arg::<_, _, Delete<FM, lifestage::LOGICAL>>(
+            |ev: KernelArg<&Delete<FM, lifestage::LOGICAL>>, index: &ColumnIndex<LM, Self>| {
+                for fid in ev.ids.iter() {
+                    let range = {
+                        let ll = Id(LM::RawId::LAST);
+                        let fl = Id(FM::RawId::LAST);
+                        let back = (IdRange::new(fid, fl), ll);
+                        ..back
+                    };
+                    if let Some(((frange, lid), ())) = index.map.range(range).next_back() {
+                        if frange.contains(fid) {
+                            panic!("can't delete {:?}: it is still referred to by {:?}", fid, lid);
+                        }
+                    }
+                }
+            },
+        );
+    }
 }
 
 /// An empty tracker for `IdRange`. Indicates that a selection of a column must be restored as a
@@ -392,6 +1098,21 @@ impl Selection {
         self.seen.insert(ty, ids);
         self.selection_order.push(ty);
     }
+    /// Add `ids` to the selection of table `M`, merging them with any that are already there.
+    /// Together with `Universe::select`, this lets you select rows of unrelated tables.
+    pub fn insert_ids<M: TableMarker>(&mut self, ids: RunList<M>) {
+        let ty = Ty::of::<M>();
+        let seen = self.seen.get_mut(&ty)
+            .and_then(|a: &mut Box<dyn AnyDebug>| {
+                let a: &mut dyn AnyDebug = &mut **a;
+                a.downcast_mut::<RunList<M>>()
+            });
+        if let Some(seen) = seen {
+            seen.merge(&ids);
+            return;
+        }
+        self.deliver_ids(Box::new(ids));
+    }
     pub fn from<FM: TableMarker>(sel: RunList<FM>) -> Self {
         let mut seen = HashMap::new();
         let ty = Ty::of::<FM>();
@@ -1,11 +1,53 @@
 //! Low-level locking.
 use crate::prelude_lib::*;
-use std::cell::UnsafeCell;
+use crate::kernel::KernelName;
+use std::borrow::Cow;
+use std::cell::{RefCell, UnsafeCell};
 use std::thread::ThreadId;
 fn thread_id() -> ThreadId {
     ::std::thread::current().id()
 }
 
+thread_local! {
+    /// Everything locked by the kernels & trackers further up this thread's stack.
+    static HELD: RefCell<Vec<(Ty, Access)>> = RefCell::new(vec![]);
+}
+/// Panics if this thread already holds any of `resources` in a way that would deadlock.
+///
+/// Trackers run inside `submit_event`, which may itself be inside a kernel's cleanup, so a
+/// tracker (or any kernel taking `UniverseRef`) that calls `eval` must not ask for anything its
+/// callers still hold. Two reads are fine; anything involving a write is not.
+pub fn assert_not_held(who: &dyn fmt::Display, resources: &[(Ty, Access)]) {
+    HELD.with(|held| {
+        for &(ty, acc) in held.borrow().iter() {
+            for &(want, wacc) in resources {
+                if ty == want && (acc == Access::Write || wacc == Access::Write) {
+                    panic!(
+                        "{} would deadlock: it wants {:?} access to {:?}, which is already held ({:?}) further up this thread's stack",
+                        who, wacc, ty, acc,
+                    );
+                }
+            }
+        }
+    })
+}
+/// Records that this thread now holds `resources`. Returns a mark for `pop_held`.
+pub fn push_held(resources: &[(Ty, Access)]) -> usize {
+    HELD.with(|held| {
+        let mut held = held.borrow_mut();
+        let mark = held.len();
+        held.extend_from_slice(resources);
+        mark
+    })
+}
+pub fn pop_held(mark: usize) {
+    HELD.with(|held| held.borrow_mut().truncate(mark))
+}
+/// Does this thread hold `ty` with `acc` access further up its stack?
+pub fn is_held(ty: Ty, acc: Access) -> bool {
+    HELD.with(|held| held.borrow().contains(&(ty, acc)))
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum LockState {
     Open,
@@ -19,10 +61,16 @@ pub struct Locked {
     pub obj: UnsafeCell<Box<dyn AnyDebug>>,
     pub state: LockState,
     pub name: Name,
+    /// The name of the kernel that has write access, or that poisoned the lock.
+    pub held_by: Option<Cow<'static, str>>,
 }
 impl fmt::Debug for Locked {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Locked({})::{:?}", self.name, self.state)
+        write!(f, "Locked({})::{:?}", self.name, self.state)?;
+        if let Some(kernel) = &self.held_by {
+            write!(f, " held by {}", kernel)?;
+        }
+        Ok(())
     }
 }
 impl Locked {
@@ -31,11 +79,22 @@ impl Locked {
             obj: UnsafeCell::new(obj),
             state: LockState::Open,
             name,
+            held_by: None,
         })
     }
     pub fn is_poisoned(&self) -> bool {
         self.state == LockState::Poison
     }
+    /// Reopens a poisoned lock, returning whether it was poisoned.
+    pub fn clear_poison(&mut self) -> bool {
+        if !self.is_poisoned() { return false; }
+        self.state = LockState::Open;
+        self.held_by = None;
+        true
+    }
+    pub fn is_write_held_by_this_thread(&self) -> bool {
+        self.state == LockState::Write(thread_id())
+    }
     // Rust does a fantastic job here.
     pub fn can(&self, access: Access) -> bool {
         match (self.state, access) {
@@ -43,7 +102,7 @@ impl Locked {
             (LockState::Read(_), Access::Read) => true,
             (LockState::Read(_), Access::Write) => false,
             (LockState::Write(orig), _) if orig == thread_id() => {
-                panic!("thread deadlock")
+                panic!("thread deadlock: {:?}", self)
             },
             (LockState::Write(_), _) => false,
             (LockState::Poison, _) => false,
@@ -65,10 +124,17 @@ impl Locked {
             (LockState::Open, Access::Read) => LockState::Read(0),
             (LockState::Open, Access::Write) => LockState::Write(thread_id()),
             (LockState::Poison, _) => {
-                panic!("acquired poisoned lock object: {:?}", self.name);
+                panic!("acquired poisoned lock object: {:?}", self);
             },
         }
     }
+    /// Like `acquire`, but remembers which kernel took write access.
+    pub fn acquire_for(&mut self, access: Access, kernel: &KernelName) {
+        self.acquire(access);
+        if access == Access::Write {
+            self.held_by = Some(kernel.name.clone());
+        }
+    }
     pub fn release(&mut self, access: Access) {
         if access == Access::Write && std::thread::panicking() {
             self.state = LockState::Poison;
@@ -80,7 +146,10 @@ impl Locked {
             (LockState::Open, access) => {
                 panic!("tried to release({:?}) a lock that is already open: {:?}", access, self.name)
             }
-            (LockState::Write(_), Access::Write) => LockState::Open,
+            (LockState::Write(_), Access::Write) => {
+                self.held_by = None;
+                LockState::Open
+            },
             (LockState::Read(0), Access::Read) => LockState::Open,
             (LockState::Read(n), Access::Read) => LockState::Read(n - 1),
             (state, access) => {
@@ -88,6 +157,24 @@ impl Locked {
             },
         }
     }
+    /// Turn a lone read lock into a write lock held by this thread. Fails if there are other
+    /// readers, or if the lock isn't read-locked at all.
+    pub fn try_upgrade(&mut self) -> bool {
+        match self.state {
+            LockState::Read(0) => {
+                self.state = LockState::Write(thread_id());
+                true
+            },
+            _ => false,
+        }
+    }
+    /// Undoes `try_upgrade`.
+    pub fn downgrade(&mut self) {
+        match self.state {
+            LockState::Write(orig) if orig == thread_id() => self.state = LockState::Read(0),
+            _ => panic!("tried to downgrade a lock that this thread isn't writing: {:?}", self),
+        }
+    }
     #[allow(clippy::borrowed_box)]
     pub unsafe fn contents(&mut self) -> *mut dyn AnyDebug {
         let obj: *mut Box<dyn AnyDebug> = self.obj.get();
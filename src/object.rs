@@ -4,6 +4,8 @@ use crate::prelude_lib::*;
 use std::collections::hash_map::Entry as MapEntry;
 use std::collections::HashMap;
 use std::sync::{Mutex, Condvar};
+use std::thread::ThreadId;
+use std::time::Duration;
 use ezty::AnyDebug;
 
 // FIXME: impl Extract for Universe.
@@ -16,6 +18,14 @@ pub struct Universe {
     pub(crate) objects: Mutex<HashMap<Ty, Box<Locked>>>,
     pub(crate) condvar: Condvar,
     pub(crate) frozen: bool,
+    pub(crate) lock_telemetry: Option<Box<dyn Fn(&str, Duration) + Send + Sync>>,
+    /// Set during `with_event_batching`.
+    pub(crate) batch: Mutex<Option<crate::event::EventBatch>>,
+    /// Objects that were added with `add_named`.
+    pub(crate) names: HashMap<Name, Ty>,
+    /// The `IdList` and columns of each table, mapped to the table's marker, which is where its
+    /// `TableHeader` is kept.
+    pub(crate) table_parts: HashMap<Ty, Ty>,
 }
 
 unsafe impl Send for Universe {}
@@ -43,10 +53,35 @@ impl Universe {
     }
     pub fn add_mut<T: AnyDebug>(&mut self, key: Ty, obj: T) {
         assert!(!self.frozen);
+        if let Some(header) = (&obj as &dyn AnyDebug).downcast_ref::<TableHeader>() {
+            self.table_parts.insert(header.ids, header.marker);
+            for col in &header.columns {
+                self.table_parts.insert(col.column_type, header.marker);
+            }
+        }
         let map = &mut *self.objects.get_mut().unwrap();
         let obj = Locked::new(Box::new(obj), std::any::type_name::<T>());
         Universe::insert(map, key, obj);
     }
+    /// Like `add_mut`, but the object can also be found by `object_by_name`. Tables add their
+    /// `IdList` under the table's name, and their columns as `"table.column"`.
+    pub fn add_named<T: AnyDebug>(&mut self, name: Name, key: Ty, obj: T) {
+        if let Some(&old) = self.names.get(name) {
+            if self.has_ty(old) {
+                panic!("{:?} is already the name of {:?}", name, old);
+            }
+        }
+        self.add_mut(key, obj);
+        self.names.insert(name, key);
+    }
+    /// Find an object that was added with `add_named`. Unlike a `TypeId`, a name stays the same
+    /// across compiler versions, so it's suitable for keying save files.
+    pub fn object_by_name(&self, name: &str) -> Option<Ty> {
+        self.names
+            .get(name)
+            .copied()
+            .filter(|&ty| self.has_ty(ty))
+    }
     pub fn remove<T: AnyDebug>(&self, key: Ty) -> Option<Box<dyn AnyDebug>> {
         assert!(!self.frozen);
         self.objects
@@ -63,6 +98,53 @@ impl Universe {
             .remove(&key)
             .map(|l| l.into_inner())
     }
+    /// Move the object stored under `from` so that it's stored under `to` instead. Unlike removing
+    /// and re-adding it, this is allowed after `freeze`. Panics if `from` is missing or `to` is
+    /// taken.
+    pub fn rekey(&mut self, from: Ty, to: Ty) {
+        let map = self.objects.get_mut().unwrap();
+        if map.contains_key(&to) {
+            panic!("can't rekey {:?} to {:?}, which is already taken", from, to);
+        }
+        let obj = map
+            .remove(&from)
+            .unwrap_or_else(|| panic!("can't rekey missing object {:?}", from));
+        map.insert(to, obj);
+        let rename = |ty: &mut Ty| if *ty == from { *ty = to; };
+        self.names.values_mut().for_each(rename);
+        if let Some(marker) = self.table_parts.remove(&from) {
+            self.table_parts.insert(to, marker);
+        }
+        self.table_parts.values_mut().for_each(rename);
+        // The headers refer to the table's parts, too.
+        for lock in map.values_mut() {
+            let obj: &mut dyn AnyDebug = &mut **lock.obj.get_mut();
+            if let Some(header) = obj.downcast_mut::<TableHeader>() {
+                rename(&mut header.marker);
+                rename(&mut header.ids);
+                header.columns.iter_mut().for_each(|col| rename(&mut col.column_type));
+            }
+        }
+    }
+    /// Run `f` with structural access to the universe, so that it can `register` or `add_mut`
+    /// things (eg to lazily create a side table on first use), and then `eval` kernels that use
+    /// them. Taking `&mut self` means that nothing else can be running kernels meanwhile, so this
+    /// is only for single-threaded use. Panics if the universe is frozen, or if anything is still
+    /// locked.
+    pub fn eval_mut<R>(&mut self, f: impl FnOnce(&mut Universe) -> R) -> R {
+        assert!(!self.frozen);
+        for (ty, lock) in self.objects.get_mut().unwrap().iter() {
+            if let LockState::Read(_) | LockState::Write(_) = lock.state {
+                panic!("eval_mut: {:?} is still locked: {:?}", ty, lock);
+            }
+        }
+        f(self)
+    }
+    /// Report how long each kernel waited for its locks to `sink`, which is given the kernel's
+    /// name. Kernels that didn't have to wait are reported too, with a short duration.
+    pub fn set_lock_telemetry(&mut self, sink: impl Fn(&str, Duration) + 'static + Send + Sync) {
+        self.lock_telemetry = Some(Box::new(sink));
+    }
     /// Disable further modification to the structure of the Universe.
     pub fn freeze(&mut self) {
         self.frozen = true;
@@ -77,12 +159,134 @@ impl Universe {
             .get(&ty)
             .is_some()
     }
+    /// How many kernels are reading `ty` right now; `None` if there's no such object. This is
+    /// only a snapshot, for debugging lock contention.
+    pub fn reader_count(&self, ty: Ty) -> Option<u64> {
+        self.objects.lock().unwrap().get(&ty).map(|lock| match lock.state {
+            // A single reader is `Read(0)`.
+            LockState::Read(n) => n + 1,
+            _ => 0,
+        })
+    }
+    /// Like `std::sync::RwLock::clear_poison`, makes `ty` usable again after a kernel panicked
+    /// while writing it. Returns whether it was poisoned. It's up to you to decide if the object
+    /// is still in a reasonable state.
+    pub fn clear_poison(&self, ty: Ty) -> bool {
+        let cleared = match self.objects.lock().unwrap().get_mut(&ty) {
+            Some(lock) => lock.clear_poison(),
+            None => false,
+        };
+        if cleared {
+            self.condvar.notify_all();
+        }
+        cleared
+    }
+    /// The thread that is writing `ty` right now, if any. Like `reader_count`, this is only a
+    /// snapshot.
+    pub fn writer(&self, ty: Ty) -> Option<ThreadId> {
+        match self.objects.lock().unwrap().get(&ty)?.state {
+            LockState::Write(thread) => Some(thread),
+            _ => None,
+        }
+    }
+    /// Has the table been registered?
+    pub fn is_registered<M: TableMarker>(&self) -> bool {
+        self.has_ty(Ty::of::<M>())
+    }
+    /// The number of live rows in a table. Only the table's `IdList` is locked, so this doesn't
+    /// have to wait for kernels that are using its columns.
+    pub fn table_len<M: TableMarker>(&self) -> usize {
+        self.with(|ids: &IdList<M>| ids.len())
+    }
+    /// Runs `f` only if the table has been registered. This lets optional subsystems work with
+    /// a table without forcing it to exist.
+    pub fn if_registered<M: TableMarker, R>(&self, f: impl FnOnce() -> R) -> Option<R> {
+        if self.is_registered::<M>() {
+            Some(f())
+        } else {
+            None
+        }
+    }
+    /// Like `R::register`, but gives an error instead of panicking if `R` was already registered.
+    /// Tables and properties are stored under their own type, so that's what's checked.
+    pub fn try_register<R: Register + 'static>(&mut self) -> Result<(), AlreadyRegistered> {
+        if self.has_ty(Ty::of::<R>()) {
+            return Err(AlreadyRegistered { name: std::any::type_name::<R>() });
+        }
+        R::register(self);
+        Ok(())
+    }
+}
+
+/// Returned by `Universe::try_register`.
+#[derive(Debug, Clone)]
+pub struct AlreadyRegistered {
+    pub name: Name,
+}
+impl fmt::Display for AlreadyRegistered {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} is already registered", self.name)
+    }
 }
+impl std::error::Error for AlreadyRegistered {}
 
 impl Universe {
-    pub fn all_mut(&mut self, mut each: impl FnMut(/*marker:*/ Ty, /*obj:*/ &mut dyn AnyDebug)) {
+    /// Get write access to `T` for the duration of `f`, from inside a kernel that only took read
+    /// access to it. Gives `None` if some other kernel is also reading `T`; no waiting is done,
+    /// as that could deadlock.
+    ///
+    /// Panics if the calling kernel doesn't hold a read lock on `T`. If `f` panics, `T` is
+    /// poisoned.
+    ///
+    /// # Safety
+    /// The kernel's `&T` (or anything else it has borrowed from `T`) must not be used while `f`
+    /// runs.
+    pub unsafe fn upgrade_with<T: AnyDebug, R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        let ty = Ty::of::<T>();
+        assert!(
+            is_held(ty, Access::Read),
+            "upgrade_with::<{}> must be called from a kernel that reads it",
+            std::any::type_name::<T>(),
+        );
+        let obj: *mut dyn AnyDebug = {
+            let mut objects = self.objects.lock().unwrap();
+            let lock = objects.get_mut(&ty).expect("object not in universe");
+            if !lock.try_upgrade() {
+                return None;
+            }
+            lock.contents()
+        };
+        struct Downgrade<'a>(&'a Universe, Ty);
+        impl<'a> Drop for Downgrade<'a> {
+            fn drop(&mut self) {
+                let mut objects = self.0.objects.lock().unwrap();
+                let lock = objects.get_mut(&self.1).unwrap();
+                if std::thread::panicking() {
+                    lock.state = LockState::Poison;
+                } else {
+                    lock.downgrade();
+                }
+            }
+        }
+        let _downgrade = Downgrade(self, ty);
+        let obj: &mut T = (*obj).downcast_mut().unwrap();
+        Some(f(obj))
+    }
+}
+
+impl Universe {
+    pub fn all_mut(&mut self, each: impl FnMut(/*marker:*/ Ty, /*obj:*/ &mut dyn AnyDebug)) {
+        self.all_matching_mut(|_| true, each)
+    }
+    pub fn all_ref(&self, each: impl FnMut(/*marker:*/ Ty, /*obj:*/ &dyn AnyDebug)) {
+        self.all_matching(|_| true, each)
+    }
+    /// Like `all_mut`, but skips objects whose `Ty` fails `pred`. The predicate is checked
+    /// before the object is locked.
+    pub fn all_matching_mut(&mut self, pred: impl Fn(Ty) -> bool, mut each: impl FnMut(/*marker:*/ Ty, /*obj:*/ &mut dyn AnyDebug)) {
         let mut objs = self.objects.lock().unwrap();
         for (marker, lock) in objs.iter_mut() {
+            if !pred(*marker) { continue; }
             unsafe {
                 let mut lock = lock.write();
                 let obj: &mut dyn AnyDebug = &mut *lock;
@@ -90,9 +294,12 @@ impl Universe {
             }
         }
     }
-    pub fn all_ref(&self, mut each: impl FnMut(/*marker:*/ Ty, /*obj:*/ &dyn AnyDebug)) {
+    /// Like `all_ref`, but skips objects whose `Ty` fails `pred`. The predicate is checked
+    /// before the object is locked.
+    pub fn all_matching(&self, pred: impl Fn(Ty) -> bool, mut each: impl FnMut(/*marker:*/ Ty, /*obj:*/ &dyn AnyDebug)) {
         let mut objs = self.objects.lock().unwrap();
         for (marker, lock) in objs.iter_mut() {
+            if !pred(*marker) { continue; }
             unsafe {
                 let lock = lock.read(/* mut. Awkard. */);
                 let obj: &dyn AnyDebug = &*lock;
@@ -147,6 +354,9 @@ impl Universe {
             let obj = objects
                 .get_mut(&ty)
                 .unwrap_or_else(|| panic!("type not found: {:?}", ty));
+            if obj.is_write_held_by_this_thread() {
+                panic!("re-entrant access to {} from the same kernel: {:?}", obj.name, obj);
+            }
             !obj.can(access)
         }).expect("with_var condvar wait failed");
         let obj = objects
@@ -167,10 +377,22 @@ impl Universe {
         };
         f(obj);
     }
+    /// Calls `f` with the key, lock state, and type name of each object, without locking any of
+    /// them; eg for a debug overlay. `f` runs while the universe's object map is locked, so it
+    /// mustn't use the universe.
+    pub fn for_each_object(&self, mut f: impl FnMut(Ty, &LockState, Name)) {
+        let objects = self.objects.lock().unwrap();
+        for (&ty, val) in objects.iter() {
+            f(ty, &val.state, val.name);
+        }
+    }
     pub fn lock_state_dump(&self) {
         let objects = self.objects.lock().unwrap();
         for (ty, val) in objects.iter() {
-            println!("    {:?}\t{:?}", ty, val.state);
+            match &val.held_by {
+                Some(kernel) => println!("    {:?}\t{:?}\theld by {}", ty, val.state, kernel),
+                None => println!("    {:?}\t{:?}", ty, val.state),
+            }
         }
     }
 }
@@ -245,6 +467,74 @@ mod test {
         });
     }
 
+    #[test]
+    fn rekey() {
+        struct PluginKey;
+        // A table's parts are found through its header.
+        struct Marker;
+        struct Ids;
+        let mut universe = Universe::new();
+        universe.add_mut(Ty::of::<String>(), format!("Hello"));
+        universe.add_mut(Ty::of::<Ids>(), 0u8);
+        universe.add_mut(Ty::of::<Marker>(), TableHeader {
+            name: "things",
+            marker: Ty::of::<Marker>(),
+            ids: Ty::of::<Ids>(),
+            ids_capacity: |_| 0,
+            columns: vec![],
+        });
+        universe.freeze();
+        universe.rekey(Ty::of::<String>(), Ty::of::<PluginKey>());
+        assert!(!universe.has::<String>());
+        universe.with_obj(Ty::of::<PluginKey>(), |obj| {
+            assert_eq!(obj.downcast_ref::<String>().unwrap(), "Hello");
+        });
+        universe.rekey(Ty::of::<Ids>(), Ty::of::<u16>());
+        universe.rekey(Ty::of::<Marker>(), Ty::of::<u32>());
+        assert_eq!(universe.table_parts.get(&Ty::of::<Ids>()), None);
+        assert_eq!(universe.table_parts[&Ty::of::<u16>()], Ty::of::<u32>());
+        universe.with_obj(Ty::of::<u32>(), |obj| {
+            let header = obj.downcast_ref::<TableHeader>().unwrap();
+            assert_eq!((header.marker, header.ids), (Ty::of::<u32>(), Ty::of::<u16>()));
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "already taken")]
+    fn rekey_onto_existing() {
+        let mut universe = Universe::new();
+        universe.add_mut(Ty::of::<String>(), format!("Hello"));
+        universe.add_mut(Ty::of::<u32>(), 3u32);
+        universe.rekey(Ty::of::<String>(), Ty::of::<u32>());
+    }
+
+    #[test]
+    fn all_matching() {
+        let mut universe = Universe::new();
+        universe.add_mut(Ty::of::<String>(), format!("Hello"));
+        universe.add_mut(Ty::of::<u32>(), 3u32);
+        let mut seen = vec![];
+        universe.all_matching(|ty| ty == Ty::of::<u32>(), |ty, obj| {
+            seen.push(ty);
+            assert_eq!(obj.downcast_ref::<u32>(), Some(&3));
+        });
+        assert_eq!(seen, vec![Ty::of::<u32>()]);
+        universe.all_matching_mut(|ty| ty == Ty::of::<String>(), |_, obj| {
+            obj.downcast_mut::<String>().unwrap().push('!');
+        });
+        assert_eq!(universe.clone_value::<String>(), "Hello!");
+    }
+
+    #[test]
+    #[should_panic(expected = "re-entrant access to")]
+    fn reentrant_with_mut() {
+        let mut universe = Universe::new();
+        universe.add_mut(Ty::of::<String>(), format!("Hello"));
+        universe.kmap(|_text: &mut String, universe: UniverseRef| {
+            universe.with_mut(|text: &mut String| text.clear());
+        });
+    }
+
     #[test]
     fn universe_claims_to_be_threadsafe() {
         fn assert<T: Send + Sync>() {}
@@ -278,6 +568,27 @@ mod test {
 /// # fn main() {}
 /// ```
 ///
+/// # Conflicts
+/// Fields may not acquire the same resource if either of them writes to it. A table's `Read`
+/// takes all of its columns, so it can't be mixed with `edit::$col` of that same table. To read
+/// some columns while editing others, name them all individually:
+/// ```
+/// # use v9::prelude::*;
+/// # #[v9::table]
+/// # struct my_table {
+/// #     pub foo: i32,
+/// #     pub bar: i32,
+/// # }
+/// #[v9::context]
+/// pub struct MyContext {
+///     ids: &self::my_table::Ids,
+///     foo: self::my_table::read::foo,
+///     bar: self::my_table::edit::bar,
+/// }
+/// # fn main() {}
+/// ```
+/// A conflict panics as soon as a kernel taking the context is made, naming the fields involved.
+///
 // We could mention that it adds a module, but that hardly seems necessary with paste. :D
 #[macro_export]
 macro_rules! decl_context {
@@ -331,6 +642,10 @@ macro_rules! decl_context {
                 }
                 unsafe impl<'a> Extract for $name<'a> {
                     fn each_resource(f: &mut dyn FnMut(Ty, Access)) {
+                        #[cfg(debug_assertions)]
+                        $crate::object::check_context(stringify!($name), &[$(
+                            (stringify!($cn), <self::cn::$cn<'static> as Extract>::each_resource),
+                        )*]);
                         $(<self::cn::$cn<'static> as Extract>::each_resource(f);)*
                     }
                     type Owned = __OwnedContext;
@@ -368,6 +683,27 @@ macro_rules! decl_context {
     };
 }
 
+/// Panics if two fields of a context acquire the same resource with conflicting access. The
+/// kernel would catch this anyway, but without saying which fields are to blame.
+#[doc(hidden)]
+#[cfg(debug_assertions)]
+pub fn check_context(context: &str, fields: &[(&str, fn(&mut dyn FnMut(Ty, Access)))]) {
+    let mut seen: Vec<(Ty, Access, &str)> = vec![];
+    for &(field, each_resource) in fields {
+        each_resource(&mut |ty, acc| {
+            for &(prev_ty, prev_acc, prev_field) in &seen {
+                if prev_ty == ty && (prev_acc == Access::Write || acc == Access::Write) {
+                    panic!(
+                        "context {}: fields `{}` ({:?}) and `{}` ({:?}) conflict on {:?}; name the columns individually instead of taking the whole table",
+                        context, prev_field, prev_acc, field, acc, ty,
+                    );
+                }
+            }
+            seen.push((ty, acc, field));
+        });
+    }
+}
+
 /// This trait is implemented by macros such as `decl_table!`. It provides a common means for
 /// adding types to the [`Universe`].
 pub trait Register {
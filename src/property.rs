@@ -1,6 +1,10 @@
 //! Singleton values.
 
 use crate::prelude_lib::*;
+use crate::kernel::Kernel;
+use std::any::Any as StdAny;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
 
 pub trait PropertyMarker: 'static + Register + Send + Sync {
     const NAME: Name;
@@ -182,6 +186,61 @@ macro_rules! decl_property {
     };
 }
 
+/// Declares a property holding an `Option<Id<M>>`, such as the currently selected entity. It
+/// starts out as `None`. Use `get_checked` (from `SelectedEntity`) to find out if the row is
+/// still there.
+///
+/// # Example
+/// ```
+/// # #[macro_use] extern crate v9;
+/// # use v9::prelude::*;
+/// #[v9::table]
+/// pub struct monsters {
+///     pub hp: u32,
+/// }
+///
+/// selected_entity! {
+///     pub TARGET: crate::monsters::Marker
+/// }
+///
+/// fn main() {
+///     let mut universe = Universe::new();
+///     monsters::Marker::register(&mut universe);
+///     TARGET::register(&mut universe);
+///     universe.kmap(|target: &TARGET, ids: &monsters::Ids| {
+///         assert!(target.get_checked(ids).is_none());
+///     });
+/// }
+/// ```
+#[macro_export]
+macro_rules! selected_entity {
+    (
+        $(#[$meta:meta])*
+        $vis:vis $name:ident: $marker:ty
+    ) => {
+        $crate::decl_property! {
+            $(#[$meta])*
+            $vis $name: ~Option<$crate::id::Id<$marker>> = None;
+        }
+    };
+}
+
+/// Implemented for the `Option<Id<M>>` held by a `selected_entity!` property.
+pub trait SelectedEntity<M: TableMarker> {
+    /// The id, if there is one and its row still exists.
+    fn get_checked<'a>(&self, ids: &'a IdList<M>) -> Option<CheckedId<'a, M>>;
+}
+impl<M: TableMarker> SelectedEntity<M> for Option<Id<M>> {
+    fn get_checked<'a>(&self, ids: &'a IdList<M>) -> Option<CheckedId<'a, M>> {
+        let id = (*self)?;
+        if id.to_usize() < ids.outer_capacity() && ids.exists(id) {
+            Some(ids.check(id))
+        } else {
+            None
+        }
+    }
+}
+
 pub unsafe trait Property: AnyDebug {}
 unsafe impl<'a, X: Property> ExtractOwned for &'a X {
     type Ty = X;
@@ -198,6 +257,137 @@ unsafe impl<'a, X: Property> ExtractOwned for &'a mut X {
     }
 }
 
+/// A flag for cooperatively cancelling long-running kernels. Such a kernel takes `&CancelToken`
+/// and polls `is_cancelled()`; any other thread may call `Universe::request_cancel()`. Nothing is
+/// preempted.
+///
+/// Take it by reference; a kernel holding `&mut CancelToken` would block `request_cancel()`.
+#[derive(Debug, Default)]
+pub struct CancelToken {
+    flag: AtomicBool,
+}
+impl CancelToken {
+    pub fn is_cancelled(&self) -> bool { self.flag.load(AtomicOrdering::Relaxed) }
+    pub fn cancel(&self) { self.flag.store(true, AtomicOrdering::Relaxed) }
+    /// Un-cancel, so that the next kernel can run.
+    pub fn reset(&self) { self.flag.store(false, AtomicOrdering::Relaxed) }
+}
+unsafe impl Property for CancelToken {}
+impl Register for CancelToken {
+    fn register(universe: &mut Universe) {
+        universe.add_mut(Ty::of::<CancelToken>(), CancelToken::default());
+    }
+}
+impl Universe {
+    /// Ask kernels polling the `CancelToken` to stop. It stays cancelled until `reset()`.
+    pub fn request_cancel(&self) {
+        self.with(CancelToken::cancel)
+    }
+}
+
+/// A limit on how much work kernels run via `Universe::run_budgeted` should do, in whatever units
+/// they agree on. Such a kernel takes `&mut Budget`, calls `consume()` as it goes, stops once
+/// `exhausted()`, and says how much it left undone with `set_remaining_work()`. This allows
+/// expensive maintenance to be spread across several frames.
+#[derive(Debug, Default, Clone)]
+pub struct Budget {
+    units: u64,
+    used: u64,
+    remaining_work: usize,
+}
+impl Budget {
+    pub fn new(units: u64) -> Self {
+        Budget { units, used: 0, remaining_work: 0 }
+    }
+    /// Spend some of the budget. Returns `false` if it is now exhausted.
+    pub fn consume(&mut self, units: u64) -> bool {
+        self.used = self.used.saturating_add(units);
+        !self.exhausted()
+    }
+    pub fn exhausted(&self) -> bool { self.used >= self.units }
+    /// The units that haven't been consumed yet.
+    pub fn left(&self) -> u64 { self.units.saturating_sub(self.used) }
+    pub fn set_remaining_work(&mut self, work: usize) { self.remaining_work = work }
+    /// The work that the kernel reported it didn't get to.
+    pub fn remaining_work(&self) -> usize { self.remaining_work }
+}
+unsafe impl Property for Budget {}
+impl Register for Budget {
+    fn register(universe: &mut Universe) {
+        universe.add_mut(Ty::of::<Budget>(), Budget::default());
+    }
+}
+impl Universe {
+    /// Runs a kernel with `budget` as its `Budget` property. Afterwards `budget` holds whatever
+    /// the kernel left of it.
+    pub fn run_budgeted(&self, kernel: &mut Kernel, budget: &mut Budget) {
+        self.with_mut(|b: &mut Budget| mem::swap(b, budget));
+        self.run(kernel);
+        self.with_mut(|b: &mut Budget| mem::swap(b, budget));
+    }
+}
+/// Reusable temporary buffers, so that kernels needn't allocate a fresh `Vec` every time they run.
+/// Take `scratch: &mut Scratch`, `take()` a buffer, and `give()` it back when done.
+///
+/// A buffer is an ordinary `Vec`; there's nothing unsafe about keeping one. But it is only
+/// recycled if it is given back, and `Universe::reset_scratch()`, which should be called once per
+/// frame, frees the buffers of any type that wasn't used since the previous reset.
+#[derive(Default)]
+pub struct Scratch {
+    pools: HashMap<Ty, Box<dyn AnyPool>>,
+}
+impl fmt::Debug for Scratch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Scratch({} pools)", self.pools.len())
+    }
+}
+struct Pool<T> {
+    free: Vec<Vec<T>>,
+    used: bool,
+}
+trait AnyPool: Send + Sync {
+    fn as_any(&mut self) -> &mut dyn StdAny;
+    fn used(&mut self) -> &mut bool;
+}
+impl<T: 'static + Send + Sync> AnyPool for Pool<T> {
+    fn as_any(&mut self) -> &mut dyn StdAny { self }
+    fn used(&mut self) -> &mut bool { &mut self.used }
+}
+impl Scratch {
+    fn pool<T: 'static + Send + Sync>(&mut self) -> &mut Pool<T> {
+        let pool = self.pools
+            .entry(Ty::of::<Vec<T>>())
+            .or_insert_with(|| Box::new(Pool::<T> { free: vec![], used: false }));
+        *pool.used() = true;
+        pool.as_any().downcast_mut().expect("type mismatch")
+    }
+    /// Get an empty `Vec`, keeping whatever capacity it had when it was given back.
+    pub fn take<T: 'static + Send + Sync>(&mut self) -> Vec<T> {
+        self.pool().free.pop().unwrap_or_default()
+    }
+    /// Return a buffer for reuse. It is cleared.
+    pub fn give<T: 'static + Send + Sync>(&mut self, mut buf: Vec<T>) {
+        buf.clear();
+        self.pool().free.push(buf);
+    }
+    /// Free the buffers of types that haven't been used since the last reset.
+    pub fn reset(&mut self) {
+        self.pools.retain(|_, pool| mem::replace(pool.used(), false));
+    }
+}
+unsafe impl Property for Scratch {}
+impl Register for Scratch {
+    fn register(universe: &mut Universe) {
+        universe.add_mut(Ty::of::<Scratch>(), Scratch::default());
+    }
+}
+impl Universe {
+    /// Call once per frame. See [`Scratch`].
+    pub fn reset_scratch(&self) {
+        self.with_mut(Scratch::reset)
+    }
+}
+
 #[doc(hidden)]
 pub mod prelude {
     pub use crate::prelude_lib::{Deref, DerefMut, Name, AnyDebug, Ty, Universe};
@@ -235,6 +425,71 @@ mod test {
             assert_eq!(prop.val, 27);
         });
     }
+
+    #[test]
+    fn cancel() {
+        let mut universe = Universe::new();
+        CancelToken::register(&mut universe);
+        let counted = universe.eval(|token: &CancelToken, universe: UniverseRef| {
+            let mut n = 0;
+            while !token.is_cancelled() {
+                n += 1;
+                if n == 10 {
+                    // Pretend to be another thread.
+                    universe.request_cancel();
+                }
+            }
+            n
+        });
+        assert_eq!(counted, 10);
+        universe.eval(|token: &CancelToken| {
+            token.reset();
+            assert!(!token.is_cancelled());
+        });
+    }
+
+    #[test]
+    fn budget() {
+        use crate::kernel::Kernel;
+        let mut universe = Universe::new();
+        Budget::register(&mut universe);
+        let mut todo = 10;
+        let mut kernel = Kernel::new(move |budget: &mut Budget| {
+            while todo > 0 && budget.consume(3) {
+                todo -= 1;
+            }
+            budget.set_remaining_work(todo);
+        });
+        let mut frames = 0;
+        loop {
+            let mut budget = Budget::new(10);
+            universe.run_budgeted(&mut kernel, &mut budget);
+            frames += 1;
+            if budget.remaining_work() == 0 { break; }
+            assert!(budget.exhausted());
+        }
+        assert_eq!(frames, 4);
+    }
+
+    #[test]
+    fn scratch() {
+        let mut universe = Universe::new();
+        Scratch::register(&mut universe);
+        let kernel = |scratch: &mut Scratch| {
+            let mut buf = scratch.take::<u32>();
+            let reused = buf.capacity();
+            buf.extend(0..100);
+            scratch.give(buf);
+            reused
+        };
+        assert_eq!(universe.eval(kernel), 0);
+        assert!(universe.eval(kernel) >= 100);
+        universe.reset_scratch();
+        assert!(universe.eval(kernel) >= 100);
+        universe.reset_scratch();
+        universe.reset_scratch();
+        assert_eq!(universe.eval(kernel), 0);
+    }
 }
 
 
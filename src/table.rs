@@ -7,6 +7,8 @@ pub struct TableHeader {
     pub name: Name,
     pub marker: Ty,
     pub ids: Ty,
+    /// Gets `outer_capacity()` of the type-erased `ids`.
+    pub ids_capacity: fn(&dyn AnyDebug) -> usize,
     pub columns: Vec<ColumnHeader>,
 }
 pub trait TableMarker: 'static + Default + Copy + Clone + Send + Sync + Register + fmt::Debug {
@@ -14,6 +16,55 @@ pub trait TableMarker: 'static + Default + Copy + Clone + Send + Sync + Register
     type RawId: Raw;
     fn header() -> TableHeader;
 }
+/// Lets generic code copy rows between tables. Implemented by `decl_table!`.
+pub trait TableRows: TableMarker {
+    type Row: 'static + Send + Sync;
+    /// Copies out the rows at `ids`, in a kernel of its own.
+    #[doc(hidden)]
+    fn clone_rows(universe: &Universe, ids: &RunList<Self>) -> Vec<Self::Row>;
+    /// Pushes `rows`, in a kernel of its own.
+    #[doc(hidden)]
+    fn push_rows(universe: &Universe, rows: Vec<Self::Row>);
+    /// A `Vec` of each column.
+    #[doc(hidden)]
+    type Columns: 'static + Send + Sync;
+    /// Same as the generated `Marker::snapshot`.
+    fn snapshot(universe: &Universe) -> TableSnapshot<Self>;
+    /// Same as the generated `Marker::restore`.
+    fn restore(universe: &Universe, snapshot: TableSnapshot<Self>);
+}
+
+/// A copy of a table's ids and columns, from `Universe::checkpoint_table`.
+pub struct TableSnapshot<M: TableRows> {
+    #[doc(hidden)]
+    pub ids: IdList<M>,
+    #[doc(hidden)]
+    pub columns: M::Columns,
+}
+impl<M: TableRows> fmt::Debug for TableSnapshot<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TableSnapshot<{}>({} rows)", M::NAME, self.ids.len())
+    }
+}
+impl Universe {
+    /// Copy every row of a table, so that it can be put back with `restore_table`. Useful for
+    /// rolling back a speculative step.
+    pub fn checkpoint_table<M: TableRows>(&self) -> TableSnapshot<M> {
+        M::snapshot(self)
+    }
+    /// Replace a table's ids and columns with a snapshot. No events are sent, so the table's
+    /// indices (including its foreign keys) and trackers aren't told; rows pointing at this table
+    /// aren't checked either. It's up to you to restore related tables together.
+    pub fn restore_table<M: TableRows>(&self, snapshot: TableSnapshot<M>) {
+        M::restore(self, snapshot)
+    }
+    /// Checks that `id` is a live row of table `M`, for validating ids from outside. Only the
+    /// table's `IdList` is locked. Ids that have been deleted don't exist, even if they're in
+    /// bounds.
+    pub fn row_exists<M: TableMarker>(&self, id: Id<M>) -> bool {
+        self.with(|ids: &IdList<M>| id.to_usize() < ids.outer_capacity() && ids.exists(id))
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct ColumnHeader {
@@ -21,6 +72,42 @@ pub struct ColumnHeader {
     pub element_type: Ty,
     pub name: Name,
     pub foreign_table: Option<Name>,
+    /// Gets the length of the type-erased column.
+    pub len: fn(&dyn AnyDebug) -> usize,
+}
+
+#[doc(hidden)]
+pub fn ids_capacity<M: TableMarker>(ids: &dyn AnyDebug) -> usize {
+    ids.downcast_ref::<IdList<M>>().expect("type mismatch").outer_capacity()
+}
+#[doc(hidden)]
+pub fn column_len<M: TableMarker, T: AnyDebug>(col: &dyn AnyDebug) -> usize {
+    col.downcast_ref::<crate::column::Column<M, T>>().expect("type mismatch").data().len()
+}
+
+#[doc(hidden)]
+#[cfg(feature = "serde")]
+#[macro_export]
+macro_rules! __v9_if_serde {
+    ($($t:tt)*) => { $($t)* };
+}
+#[doc(hidden)]
+#[cfg(not(feature = "serde"))]
+#[macro_export]
+macro_rules! __v9_if_serde {
+    ($($t:tt)*) => {};
+}
+
+/// Sends the `Push` events for rows that were just loaded by `deserialize_table`.
+#[doc(hidden)]
+#[cfg(feature = "serde")]
+pub fn submit_load_events<M: TableMarker>(universe: &Universe, ids: RunList<M>) {
+    use crate::event::{lifestage, Push, Unsafe};
+    if ids.is_empty() { return; }
+    let mut event = Push { lifestage: unsafe { Unsafe::new(lifestage::MEMORY) }, ids };
+    universe.submit_event(&mut event);
+    let mut event = Push { lifestage: unsafe { Unsafe::new(lifestage::LOAD) }, ids: event.ids };
+    universe.submit_event(&mut event);
 }
 
 /// Defines a table. This is the most important item in the crate!
@@ -155,10 +242,27 @@ pub struct ColumnHeader {
 ///    consider wrapping it in an `Arc`, or something that panics.)
 /// 3. `#[raw_index(u32)]`. Defines the type used to index. The default is `u32`. Must be [`Raw`].
 ///    The last index is generally considered to be 'invalid'.
+/// 4. `#[dense]`. Removed rows are filled in by moving the last rows down, instead of leaving a
+///    hole, so that the ids are always `0..len`. A `Move` event is sent after each compaction.
+///    Don't hold on to ids across kernels unless they are
+///    foreign keys or indexed, as those are updated. (Without `#[dense]`, `Write::swap_remove`
+///    fills a single hole the same way.)
+/// 5. `#[kind(column)]`. Indexes the rows by `column`, which is typically an enum with
+///    `Ord + Copy`. This generates `KindIndex`, which kernels can take by reference, and
+///    `iter_kind(&KindIndex, k)`, which iterates over the rows where `column == k`.
+/// 6. `#[on_delete(restrict)]`. Deleting a foreign row that is still referred to by this table
+///    panics, instead of deleting the referring rows. This applies to every foreign key in the
+///    table. `#[on_delete(cascade)]` is the default.
+///
+/// `#[dense]`, `#[kind]`, and `#[on_delete]` may be given in any order.
 ///
-/// Any attributes on the columns will be passed as-is to the fields on `Row`.
+/// Any attributes on the columns will be passed as-is to the fields on `Row`, except for
+/// `#[sparse]`. A `#[sparse]` column is kept in a [`SparseColumn`] instead, and isn't part of
+/// `Row`, `Read`, or `Write`; kernels take it as `&my_table::sparse::column`. It may only have
+/// documentation besides, and that must come first.
 ///
 /// [`Raw`]: id/trait.Raw.html
+/// [`SparseColumn`]: column/struct.SparseColumn.html
 ///
 /// ## Example
 ///
@@ -181,14 +285,109 @@ pub struct ColumnHeader {
 // FIXME: keep the stinky_cheeses example in sync or something...?
 #[macro_export]
 macro_rules! decl_table {
+    (@flag dense) => {};
+    (@flag kind($col:ident)) => {};
+    (@flag on_delete(cascade)) => {};
+    (@flag on_delete(restrict)) => {};
+    (@if_restrict [] { $($t:tt)* }) => {};
+    (@if_restrict [[on_delete(restrict)] $($rest:tt)*] { $($t:tt)* }) => { $($t)* };
+    (@if_restrict [[$($other:tt)*] $($rest:tt)*] { $($t:tt)* }) => {
+        $crate::decl_table! { @if_restrict [$($rest)*] { $($t)* } }
+    };
+    (@if_dense [] { $($t:tt)* }) => {};
+    (@if_dense [[dense] $($rest:tt)*] { $($t:tt)* }) => { $($t)* };
+    (@if_dense [[$($other:tt)*] $($rest:tt)*] { $($t:tt)* }) => {
+        $crate::decl_table! { @if_dense [$($rest)*] { $($t)* } }
+    };
+    (@kind_items []) => {};
+    (@kind_items [[kind($col:ident)] $($rest:tt)*]) => {
+        /// Index on the `#[kind]` column. Kernels should take this by reference.
+        pub type KindIndex = $crate::prelude_macro::ColumnIndex<super::Marker, self::types::$col>;
+        /// Iterate over the rows of kind `k`, in order.
+        pub fn iter_kind(index: &KindIndex, k: self::types::$col) -> impl DoubleEndedIterator<Item=super::Id> + '_ {
+            index.find(k)
+        }
+    };
+    (@kind_items [[$($other:tt)*] $($rest:tt)*]) => {
+        $crate::decl_table! { @kind_items [$($rest)*] }
+    };
+    (@kind_register [] $universe:ident) => {};
+    (@kind_register [[kind($col:ident)] $($rest:tt)*] $universe:ident) => {
+        $universe.add_index::<super::Marker, self::types::$col>();
+    };
+    (@kind_register [[$($other:tt)*] $($rest:tt)*] $universe:ident) => {
+        $crate::decl_table! { @kind_register [$($rest)*] $universe }
+    };
+    // Pulls the `#[sparse]` columns out from the others.
+    (@split $head:tt [$($cols:tt)*] [$($sparse:tt)*] {}) => {
+        $crate::decl_table! { @table $head { $($cols)* } sparse { $($sparse)* } }
+    };
+    (@split $head:tt $cols:tt [$($sparse:tt)*] {
+        $(#[doc = $sdoc:literal])*
+        #[sparse]
+        pub $sn:ident: $sty:ty,
+        $($rest:tt)*
+    }) => {
+        $crate::decl_table! {
+            @split $head $cols [$($sparse)* $(#[doc = $sdoc])* pub $sn: $sty,] { $($rest)* }
+        }
+    };
+    (@split $head:tt [$($cols:tt)*] $sparse:tt {
+        $(#[$cmeta:meta])*
+        pub $cn:ident: $cty:ty,
+        $($rest:tt)*
+    }) => {
+        $crate::decl_table! {
+            @split $head [$($cols)* $(#[$cmeta])* pub $cn: $cty,] $sparse { $($rest)* }
+        }
+    };
+    (
+        $(#[doc = $doc:literal])*
+        $(#[row::$row_meta:meta])*
+        #[dense]
+        $($rest:tt)*
+    ) => {
+        $crate::decl_table! {
+            $(#[doc = $doc])*
+            $(#[row::$row_meta])*
+            #[raw_index(u32)]
+            #[dense]
+            $($rest)*
+        }
+    };
+    (
+        $(#[doc = $doc:literal])*
+        $(#[row::$row_meta:meta])*
+        #[kind($col:ident)]
+        $($rest:tt)*
+    ) => {
+        $crate::decl_table! {
+            $(#[doc = $doc])*
+            $(#[row::$row_meta])*
+            #[raw_index(u32)]
+            #[kind($col)]
+            $($rest)*
+        }
+    };
+    (
+        $(#[doc = $doc:literal])*
+        $(#[row::$row_meta:meta])*
+        #[on_delete($policy:ident)]
+        $($rest:tt)*
+    ) => {
+        $crate::decl_table! {
+            $(#[doc = $doc])*
+            $(#[row::$row_meta])*
+            #[raw_index(u32)]
+            #[on_delete($policy)]
+            $($rest)*
+        }
+    };
     (
         $(#[doc = $doc:literal])*
         $(#[row::$row_meta:meta])*
         $vis:vis struct $name:ident {
-            $(
-                $(#[$cmeta:meta])*
-                pub $cn:ident: $cty:ty,
-            )*
+            $($body:tt)*
         }
     ) => {
         $crate::decl_table! {
@@ -196,10 +395,7 @@ macro_rules! decl_table {
             $(#[row::$row_meta])*
             #[raw_index(u32)]
             $vis struct $name {
-                $(
-                    $(#[$cmeta])*
-                    pub $cn: $cty,
-                )*
+                $($body)*
             }
         }
     };
@@ -207,23 +403,52 @@ macro_rules! decl_table {
         $(#[doc = $doc:literal])*
         $(#[row::$row_meta:meta])*
         #[raw_index($raw:ty)]
+        $(#[$flag:ident $(($flag_arg:ident))?])*
         $vis:vis struct $name:ident {
+            $($body:tt)*
+        }
+        // FIXME: `in mod $in_mod:tt`
+    ) => {
+        $crate::decl_table! {
+            @split [
+                $(#[doc = $doc])*
+                $(#[row::$row_meta])*
+                #[raw_index($raw)]
+                $(#[$flag $(($flag_arg))?])*
+                $vis struct $name
+            ] [] [] { $($body)* }
+        }
+    };
+    (
+        @table [
+            $(#[doc = $doc:literal])*
+            $(#[row::$row_meta:meta])*
+            #[raw_index($raw:ty)]
+            $(#[$flag:ident $(($flag_arg:ident))?])*
+            $vis:vis struct $name:ident
+        ] {
             $(
                 $(#[$cmeta:meta])*
                 pub $cn:ident: $cty:ty,
             )*
+        } sparse {
+            $(
+                $(#[doc = $sdoc:literal])*
+                pub $sn:ident: $sty:ty,
+            )*
         }
-        // FIXME: `in mod $in_mod:tt`
     ) => {
-        #[allow(non_camel_case_types, dead_code, non_upper_case_globals, non_snake_case)]
+        // The unused lints are for tables without columns.
+        #[allow(non_camel_case_types, dead_code, non_upper_case_globals, non_snake_case, unused_unsafe, unused_variables)]
         $(#[doc = $doc])*
         $vis mod $name {
+            $($crate::decl_table! { @flag $flag $(($flag_arg))? })*
             // Annoyingly, we have to firewall out v9 types from the user's.
             // We could do `$crate::prelude_macro::Thing` instead but it's horrifically ugly, and
             // it gets *everywhere*.
             mod in_v9 {
                 use $crate::prelude_macro::*;
-                use super::in_user::{Read, Write, Edit, Row, RowRef};
+                use super::in_user::{Read, Write, Edit, Row, RowRef, RmRow};
                 /// Table's name.
                 pub const NAME: &'static str = stringify!($name);
                 /// A strongly typed index into the table.
@@ -273,11 +498,42 @@ macro_rules! decl_table {
                         let i = self.ids().check(i);
                         RowRef {
                             $($cn: &self.$cn[i],)*
+                            __v9_lifetime: ::std::marker::PhantomData,
                         }
                     }
+                    /// Like `ref_row`, but gives `None` instead of panicking if the row doesn't
+                    /// exist. Useful for following foreign keys through data that may be bad.
+                    pub fn try_ref_row(&self, i: impl 'a + Check<M=Marker>) -> Option<RowRef> {
+                        let id = IdV9(i.to_raw());
+                        if id.to_usize() >= self.ids().outer_capacity() || !self.ids().exists(id) {
+                            return None;
+                        }
+                        Some(self.ref_row(i))
+                    }
                     pub fn iter(&self) -> CheckedIter<Marker> {
                         self.__v9__iter.iter()
                     }
+                    /// Iterate over the live rows, with a `RowRef` for each. The columns'
+                    /// lengths are checked once up front, instead of on every access.
+                    pub fn zip(&self) -> impl Iterator<Item=(Id, RowRef<'a>)> + 'a {
+                        let cap = self.ids().outer_capacity();
+                        let read = Read {
+                            $($cn: ReadColumn { col: self.$cn.col },)*
+                            __v9__iter: self.__v9__iter,
+                        };
+                        $(assert!(read.$cn.col.data().len() >= cap, "column {} is too short", names::$cn);)*
+                        self.__v9__iter.iter().map(move |id| unsafe {
+                            let i = id.to_usize();
+                            (id.uncheck(), RowRef {
+                                $($cn: read.$cn.col.data().get_unchecked(i),)*
+                                __v9_lifetime: ::std::marker::PhantomData,
+                            })
+                        })
+                    }
+                    /// Iterate over the rows that are in `sel`.
+                    pub fn iter_selected<'b>(&'b self, sel: &'b RunList<Marker>) -> SelectedIter<'b, Marker> {
+                        self.__v9__iter.iter_selected(sel)
+                    }
                 }
                 impl<'a> Edit<'a> {
                     pub fn clone_row(&self, i: impl 'a + Check<M=Marker>) -> Row {
@@ -287,6 +543,7 @@ macro_rules! decl_table {
                         // We can't actually check.
                         RowRef {
                             $($cn: &self.$cn[i],)*
+                            __v9_lifetime: ::std::marker::PhantomData,
                         }
                     }
                     pub fn borrow(&'a self, ids: &'a Ids) -> Read<'a> {
@@ -304,6 +561,7 @@ macro_rules! decl_table {
                         // We can't actually check.
                         RowRef {
                             $($cn: &self.$cn[i],)*
+                            __v9_lifetime: ::std::marker::PhantomData,
                         }
                     }
                     #[inline]
@@ -328,11 +586,32 @@ macro_rules! decl_table {
                         // what you want to kill, and then kill them later.
                         self.__v9__iter
                     }
+                    /// Make room for `n` more rows. Panics if the ids would run out.
                     pub fn reserve(&mut self, n: usize) {
+                        self.__v9__iter.reserve(n);
                         unsafe {
                             $(self.$cn.col.get_mut().data_mut().reserve(n);)*
                         }
                     }
+                    /// Release the memory held by removed rows at the end of the table, and
+                    /// any excess capacity.
+                    pub fn shrink_to_fit(&mut self) {
+                        let len = self.__v9__iter.trim();
+                        unsafe {
+                            $({
+                                let data = self.$cn.col.get_mut().data_mut();
+                                data.truncate(len);
+                                data.shrink_to_fit();
+                            })*
+                        }
+                    }
+                    /// How many rows the columns can hold without reallocating. This is
+                    /// `usize::MAX` if there are no columns.
+                    pub fn capacity(&self) -> usize {
+                        let capacity = usize::MAX;
+                        $(let capacity = capacity.min(self.$cn.col.data().capacity());)*
+                        capacity
+                    }
                     pub fn push(&mut self, row: Row) -> Id {
                         unsafe {
                             match self.__v9__iter.recycle_id(true) {
@@ -367,7 +646,10 @@ macro_rules! decl_table {
                             let row = rows.next().expect($crate::util::die::BAD_ITER_LEN);
                             unsafe { self.set_immediate(id.to_usize(), row); }
                         }
-                        self.reserve(recycle.extend);
+                        // The ids are already taken, so only the columns need room.
+                        unsafe {
+                            $(self.$cn.col.get_mut().data_mut().reserve(recycle.extend);)*
+                        }
                         for _ in 0..recycle.extend {
                             let row = rows.next().expect($crate::util::die::BAD_ITER_LEN);
                             unsafe { self.push_immediate(row); }
@@ -385,9 +667,40 @@ macro_rules! decl_table {
                         // FIXME: This probably needs more testing.
                         self.__v9__iter.delete(i.into());
                     }
+                    /// Remove every row in `set`, as a single `Delete` event.
+                    pub fn remove_set(&mut self, set: &RunList<Marker>) {
+                        self.__v9__iter.delete_extend_ranges(set.iter_runs_inclusive());
+                    }
                     pub fn iter(&self) -> CheckedIter<Marker> {
                         self.__v9__iter.iter()
                     }
+                    /// Like `ids_mut().removing()`, but each item can also look at its row.
+                    pub fn removing_rows<'b>(&'b mut self) -> impl Iterator<Item=RmRow<'b>> + 'b {
+                        // The columns are borrowed separately from the ids, so the rows can be
+                        // read while the deleter is held.
+                        $(let $cn: &'b [_] = self.$cn.col.data();)*
+                        self.__v9__iter.removing().map(move |rm| {
+                            let i = rm.id().to_usize();
+                            RmRow {
+                                row: RowRef {
+                                    $($cn: &$cn[i],)*
+                                    __v9_lifetime: ::std::marker::PhantomData,
+                                },
+                                rm,
+                            }
+                        })
+                    }
+                    /// Remove every row for which `f` returns `false`, as a single `Delete` event.
+                    pub fn retain(&mut self, mut f: impl FnMut(Id, RowRef) -> bool) {
+                        let mut doomed = RunList::new();
+                        // `doomed` has to be built in ascending order.
+                        for id in self.__v9__iter.sorted_iter() {
+                            if !f(id.uncheck(), self.ref_row(id)) {
+                                doomed.push(id.uncheck());
+                            }
+                        }
+                        self.remove_set(&doomed);
+                    }
                     pub fn clear(&mut self) {
                         // FIXME: Crap impl
                         let to_delete = self.iter().map(|i| i.uncheck()).collect::<Vec<_>>();
@@ -395,6 +708,59 @@ macro_rules! decl_table {
                             self.remove(id);
                         }
                     }
+                    /// Remove every row. Rather than a `Delete` event, a single `Cleared` event is
+                    /// sent, and the columns are emptied. Trackers of the `LOGICAL` or `LOAD`
+                    /// `Delete` events need to see every id, so if there are any (for instance,
+                    /// if another table has an `IdRange` foreign key to this one) this acts like
+                    /// `clear`. Rows with an `Id` foreign key to this table are still removed.
+                    pub fn clear_fast(&mut self) {
+                        self.__v9__iter.clear_fast();
+                    }
+                    /// Remove every row, and start the ids over from `FIRST`. Unlike `clear_fast`,
+                    /// the usual `Delete` events are sent, so foreign keys cascade as normal;
+                    /// afterwards a `Cleared` event is sent and the columns are emptied. If rows
+                    /// are pushed after this in the same kernel, the ids aren't started over.
+                    pub fn reset(&mut self) {
+                        self.__v9__iter.reset();
+                    }
+                    /// Empties the columns after a `Cleared` event.
+                    #[doc(hidden)]
+                    pub fn __v9_apply_clear(&mut self) {
+                        unsafe {
+                            $(self.$cn.col.get_mut().data_mut().clear();)*
+                        }
+                    }
+                    /// Remove a row, and fill in the hole by moving the row with the highest
+                    /// id into it. This happens once the kernel finishes, like `move_row`; then
+                    /// a `Move` event is sent. Other holes in the table are left alone.
+                    pub fn swap_remove(&mut self, i: impl Into<Id>) {
+                        let i = i.into();
+                        self.__v9__iter.delete(i);
+                        self.__v9__iter.request_swap(i);
+                    }
+                    /// Move the row at `from` to `to`, which must be free (eg, a removed row's
+                    /// id). This happens once the kernel finishes, after any pushes or removals;
+                    /// then a `Move` event is sent, and foreign keys & indices follow the row.
+                    /// Panics then if `from` doesn't exist, if `to` isn't free, or if an
+                    /// `IdRange` foreign key needs this table's rows to stay put.
+                    pub fn move_row(&mut self, from: impl Into<Id>, to: impl Into<Id>) {
+                        self.__v9__iter.request_move(from.into(), to.into());
+                    }
+                    /// Moves the column data to follow an `IdList::compact()` or
+                    /// `IdList::take_moves()`.
+                    #[doc(hidden)]
+                    pub fn __v9_apply_moves(&mut self, moves: &[(Id, Id)]) {
+                        let len = self.__v9__iter.outer_capacity();
+                        unsafe {
+                            $({
+                                let data = self.$cn.col.get_mut().data_mut();
+                                for &(old, new) in moves {
+                                    data.swap(old.to_usize(), new.to_usize());
+                                }
+                                data.truncate(len);
+                            })*
+                        }
+                    }
                 }
             }
             #[allow(unused_imports)]
@@ -413,6 +779,7 @@ macro_rules! decl_table {
                             name: Self::NAME,
                             marker: $crate::prelude_macro::Ty::of::<super::Marker>(),
                             ids: $crate::prelude_macro::Ty::of::<super::Ids>(),
+                            ids_capacity: $crate::table::ids_capacity::<super::Marker>,
                             columns: vec![$($crate::prelude_macro::ColumnHeader {
                                 column_type: $crate::prelude_macro::Ty::of::<self::own::$cn>(),
                                 element_type: $crate::prelude_macro::Ty::of::<self::types::$cn>(),
@@ -421,32 +788,242 @@ macro_rules! decl_table {
                                     type T = $cty;
                                     T::__v9_link_foreign_table_name()
                                 },
+                                len: $crate::table::column_len::<super::Marker, self::types::$cn>,
                             }),*],
                         }
                     }
                 }
+                impl $crate::prelude_macro::TableRows for super::Marker {
+                    type Row = super::Row;
+                    fn clone_rows(
+                        universe: &$crate::prelude_macro::Universe,
+                        ids: &$crate::prelude_macro::RunList<super::Marker>,
+                    ) -> Vec<super::Row> {
+                        universe.eval(|edit: super::Edit| ids.iter().map(|id| edit.clone_row(id)).collect())
+                    }
+                    fn push_rows(universe: &$crate::prelude_macro::Universe, rows: Vec<super::Row>) {
+                        universe.eval(move |mut write: super::Write| {
+                            for row in rows {
+                                write.push(row);
+                            }
+                        });
+                    }
+                    type Columns = __V9Columns;
+                    fn snapshot(universe: &$crate::prelude_macro::Universe) -> $crate::table::TableSnapshot<super::Marker> {
+                        super::Marker::snapshot(universe)
+                    }
+                    fn restore(universe: &$crate::prelude_macro::Universe, snapshot: $crate::table::TableSnapshot<super::Marker>) {
+                        super::Marker::restore(universe, snapshot)
+                    }
+                }
+                impl<'a> super::Write<'a> {
+                    /// Push `n` rows with every column set to its `Default`, giving their ids,
+                    /// which are contiguous. Only callable if every column's type is `Default`.
+                    pub fn push_n(&mut self, n: usize) -> super::Range
+                    where
+                        // The `for` keeps the bound from being checked unless this is called.
+                        $(for<'__v9> self::types::$cn: Default,)*
+                    {
+                        self.push_contiguous((0..n).map(|_| Row {
+                            $($cn: Default::default(),)*
+                        }))
+                    }
+                }
+                #[doc(hidden)]
+                pub struct __V9Columns {
+                    $($cn: Vec<$cty>,)*
+                }
+                $crate::__v9_if_serde! {
+                    const __V9_FIELDS: &[&str] = &["__v9_live", $(stringify!($cn)),*];
+                    struct __V9Visitor;
+                    impl<'de> $crate::__serde::de::Visitor<'de> for __V9Visitor
+                    where
+                        $(self::types::$cn: $crate::__serde::Deserialize<'de>,)*
+                    {
+                        type Value = ($crate::prelude_macro::RunList<super::Marker>, __V9Columns);
+                        fn expecting(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                            write!(f, "the {} table", super::NAME)
+                        }
+                        fn visit_seq<A: $crate::__serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                            use $crate::__serde::de::Error;
+                            let mut n = 0;
+                            let mut next = || {
+                                n += 1;
+                                A::Error::invalid_length(n - 1, &self)
+                            };
+                            let live = seq.next_element()?.ok_or_else(&mut next)?;
+                            let columns = __V9Columns {
+                                $($cn: seq.next_element()?.ok_or_else(&mut next)?,)*
+                            };
+                            Ok((live, columns))
+                        }
+                        fn visit_map<A: $crate::__serde::de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                            use $crate::__serde::de::Error;
+                            let mut live = None;
+                            struct Partial {
+                                $($cn: Option<Vec<$cty>>,)*
+                            }
+                            let mut partial = Partial {
+                                $($cn: None,)*
+                            };
+                            while let Some(key) = map.next_key::<String>()? {
+                                if key == __V9_FIELDS[0] {
+                                    live = Some(map.next_value()?);
+                                    continue;
+                                }
+                                $(if key == stringify!($cn) {
+                                    partial.$cn = Some(map.next_value()?);
+                                    continue;
+                                })*
+                                map.next_value::<$crate::__serde::de::IgnoredAny>()?;
+                            }
+                            let live = live.ok_or_else(|| A::Error::missing_field(__V9_FIELDS[0]))?;
+                            let columns = __V9Columns {
+                                $($cn: partial.$cn.ok_or_else(|| A::Error::missing_field(stringify!($cn)))?,)*
+                            };
+                            Ok((live, columns))
+                        }
+                    }
+                }
+                impl super::Marker {
+                    /// Copy every row of the table. See `Universe::checkpoint_table`.
+                    pub fn snapshot(universe: &$crate::prelude_macro::Universe) -> $crate::table::TableSnapshot<super::Marker> {
+                        universe.eval(|table: super::Read| {
+                            $crate::table::TableSnapshot {
+                                ids: table.ids().clone(),
+                                columns: __V9Columns {
+                                    $($cn: table.$cn.col.data().clone(),)*
+                                },
+                            }
+                        })
+                    }
+                    $crate::__v9_if_serde! {
+                        /// Serialize the whole table as a struct with the live ids, and then a
+                        /// `Vec` for each column. Requires the `serde` feature.
+                        pub fn serialize_table<S>(universe: &$crate::prelude_macro::Universe, serializer: S) -> Result<S::Ok, S::Error>
+                        where
+                            S: $crate::__serde::Serializer,
+                            $(for<'__v9> self::types::$cn: $crate::__serde::Serialize,)*
+                        {
+                            use $crate::__serde::ser::SerializeStruct;
+                            universe.eval(move |table: super::Read| {
+                                let live = table.ids().live_ids();
+                                let mut state = serializer.serialize_struct(super::NAME, __V9_FIELDS.len())?;
+                                state.serialize_field(__V9_FIELDS[0], &live)?;
+                                $(state.serialize_field(stringify!($cn), table.$cn.col.data())?;)*
+                                state.end()
+                            })
+                        }
+                        /// Load a table written by `serialize_table`. The table must be empty.
+                        /// `LOAD` `Push` events are sent for the rows. Requires the `serde`
+                        /// feature.
+                        pub fn deserialize_table<'de, D>(universe: &$crate::prelude_macro::Universe, deserializer: D) -> Result<(), D::Error>
+                        where
+                            D: $crate::__serde::Deserializer<'de>,
+                            $(for<'__v9> self::types::$cn: $crate::__serde::de::DeserializeOwned,)*
+                        {
+                            use $crate::__serde::de::Error;
+                            use $crate::prelude_macro::Check as _;
+                            let (live, columns) = deserializer.deserialize_struct(super::NAME, __V9_FIELDS, __V9Visitor)?;
+                            let mut capacity = None;
+                            $({
+                                let len = columns.$cn.len();
+                                if *capacity.get_or_insert(len) != len {
+                                    return Err(D::Error::custom(format!("column {} has the wrong length", super::names::$cn)));
+                                }
+                            })*
+                            let capacity = capacity.unwrap_or_else(|| {
+                                live.iter().last().map_or(0, |id: super::Id| id.to_usize() + 1)
+                            });
+                            let live = universe.eval(move |mut table: super::Write| -> Result<_, String> {
+                                table.ids_mut().load_live(&live, capacity)?;
+                                unsafe {
+                                    $(*table.$cn.col.get_mut().data_mut() = columns.$cn;)*
+                                }
+                                Ok(live)
+                            }).map_err(D::Error::custom)?;
+                            $crate::table::submit_load_events(universe, live);
+                            Ok(())
+                        }
+                    }
+                    /// Put back a snapshot. See `Universe::restore_table`.
+                    pub fn restore(universe: &$crate::prelude_macro::Universe, snapshot: $crate::table::TableSnapshot<super::Marker>) {
+                        universe.eval(move |mut table: super::Write| {
+                            let $crate::table::TableSnapshot { ids, columns } = snapshot;
+                            *table.ids_mut() = ids;
+                            unsafe {
+                                $(*table.$cn.col.get_mut().data_mut() = columns.$cn;)*
+                            }
+                        });
+                    }
+                }
                 impl $crate::prelude_macro::Register for super::Marker {
                     fn register(universe: &mut $crate::prelude_macro::Universe) {
                         universe.add_mut(
                             $crate::prelude_macro::Ty::of::<super::Marker>(),
                             <Self as $crate::prelude_macro::TableMarker>::header(),
                         );
-                        universe.add_mut(
+                        let mut ids = $crate::prelude_macro::IdList::<super::Marker>::default();
+                        $crate::decl_table! { @if_dense [$([$flag $(($flag_arg))?])*] {
+                            ids.set_dense();
+                        }}
+                        universe.add_named(
+                            super::NAME,
                             $crate::prelude_macro::Ty::of::<$crate::prelude_macro::IdList<super::Marker>>(),
-                            $crate::prelude_macro::IdList::<super::Marker>::default(),
+                            ids,
                         );
                         // Interesting that we can't have duplicate types, hmm?
-                        $(universe.add_mut(
+                        $(universe.add_named(
+                                super::names::$cn,
                                 $crate::prelude_macro::Ty::of::<$crate::prelude_macro::Column<super::Marker, $cty>>(),
                                 $crate::prelude_macro::Column::<super::Marker, $cty>::new(),
                         );)*
+                        universe.add_tracker_with_ref_arg::<_, _, $crate::prelude_macro::Cleared<super::Marker>>(
+                            |_ev: $crate::prelude_macro::KernelArg<&$crate::prelude_macro::Cleared<super::Marker>>, mut table: super::Write| {
+                                table.__v9_apply_clear();
+                            },
+                        );
+                        // This must be the first tracker, so that the data has moved before
+                        // any index looks at it.
+                        universe.add_tracker_with_ref_arg::<_, _, $crate::prelude_macro::Move<super::Marker>>(
+                            |ev: $crate::prelude_macro::KernelArg<&$crate::prelude_macro::Move<super::Marker>>, mut table: super::Write| {
+                                table.__v9_apply_moves(&ev.ids);
+                            },
+                        );
+                        $crate::decl_table! { @kind_register [$([$flag $(($flag_arg))?])*] universe }
+                        // Before the cascade, so that nothing's been deleted yet.
+                        $crate::decl_table! { @if_restrict [$([$flag $(($flag_arg))?])*] {
+                            $({
+                                type T = $cty;
+                                T::__v9_restrict_foreign_key::<super::Marker>(universe);
+                            })*
+                        }}
                         $({
                             type T = $cty;
                             T::__v9_link_foreign_key::<super::Marker>(universe);
                         })*
+                        $(universe.add_sparse_column::<super::Marker, $sty>();)*
                     }
                 }
 
+                /// Start recording the ids of rows that are pushed, edited, or removed into a
+                /// `TouchLog<Marker>`. Editing the table will be somewhat slower.
+                pub fn track_touches(universe: &mut $crate::prelude_macro::Universe) {
+                    universe.add_touch_log::<super::Marker>();
+                    $(universe.add_touch_log_column::<super::Marker, self::types::$cn>();)*
+                }
+
+                /// Push a row, and flush it before returning, so that the `Push` events have
+                /// been handled (and indices updated) by the time you get the `Id`.
+                ///
+                /// This isn't a method on `Write` because the `Push` trackers need to read the
+                /// columns that a `Write` holds locked. Instead it runs its own little kernel, so
+                /// it may be called from a kernel that takes `UniverseRef`, as long as that kernel
+                /// doesn't hold this table or anything its trackers use.
+                pub fn push_tracked(universe: &$crate::prelude_macro::Universe, row: Row) -> super::Id {
+                    universe.eval(move |mut table: Write| table.push(row))
+                }
+
                 // FIXME: Maybe we shouldn't have these by default...
                 #[derive(Debug, Clone)]
                 $(#[$row_meta])*
@@ -464,6 +1041,8 @@ macro_rules! decl_table {
                 #[derive(Debug, Clone)]
                 pub struct RowRef<'a> {
                     $(pub $cn: &'a $cty,)*
+                    #[doc(hidden)]
+                    pub __v9_lifetime: ::std::marker::PhantomData<&'a ()>,
                 }
                 impl<'a> RowRef<'a> {
                     #[inline]
@@ -473,6 +1052,20 @@ macro_rules! decl_table {
                         }
                     }
                 }
+                /// A row from `Write::removing_rows`, which can be removed.
+                #[derive(Debug)]
+                pub struct RmRow<'a> {
+                    pub row: RowRef<'a>,
+                    rm: $crate::id::RmId<'a, super::Marker>,
+                }
+                impl<'a> RmRow<'a> {
+                    pub fn id(&self) -> super::Id {
+                        self.rm.id()
+                    }
+                    pub fn remove(self) {
+                        self.rm.remove()
+                    }
+                }
 
                 /// The type of the element of a column.
                 pub mod types {
@@ -480,6 +1073,16 @@ macro_rules! decl_table {
                     use super::super::super::*;
                     $(pub type $cn = $cty;)*
                 }
+                /// The `#[sparse]` columns. Kernels take these by reference.
+                pub mod sparse {
+                    #[allow(unused_imports)]
+                    use super::super::super::*;
+                    $(
+                        $(#[doc = $sdoc])*
+                        pub type $sn = $crate::prelude_macro::SparseColumn<super::super::in_v9::Marker, $sty>;
+                    )*
+                }
+                $crate::decl_table! { @kind_items [$([$flag $(($flag_arg))?])*] }
                 /// The type of the columns that are actually stored in the universe.
                 /// You'll usually want `read::MyColumn` or `edit::MyColumn`.
                 pub mod own {
@@ -501,6 +1104,8 @@ macro_rules! decl_table {
                 /// Edit an individual column.
                 pub mod edit {
                     $(pub type $cn<'a> = $crate::prelude_macro::EditColumn<'a, super::super::in_v9::Marker, super::types::$cn>;)*
+                    #[doc(hidden)]
+                    pub type __V9__Lifetime<'a> = $crate::prelude_macro::NoResources<'a>;
                     $crate::decl_context! {
                         /// Modification-access to the elements of a table.
                         ///
@@ -511,6 +1116,8 @@ macro_rules! decl_table {
                         /// column, you might consider `_: my_table::edit::specific_column`.
                         pub struct __Edit {
                             $(pub $cn: $cn,)*
+                            #[doc(hidden)]
+                            pub(in super::super::super) __v9__lifetime: __V9__Lifetime,
                         }
                     }
                 }
@@ -564,6 +1171,69 @@ mod test {
         bobs::Marker::register(&mut universe);
         bobs::Marker::register(&mut universe);
     }
+    #[test]
+    fn if_registered() {
+        let mut universe = Universe::new();
+        assert_eq!(universe.if_registered::<bobs::Marker, _>(|| 1), None);
+        bobs::Marker::register(&mut universe);
+        assert_eq!(universe.if_registered::<bobs::Marker, _>(|| 1), Some(1));
+    }
+    #[test]
+    fn reader_count() {
+        let mut universe = Universe::new();
+        bobs::Marker::register(&mut universe);
+        let ty = Ty::of::<bobs::Marker>();
+        let acquire = |universe: &Universe| universe.objects.lock().unwrap().get_mut(&ty).unwrap().acquire(Access::Read);
+        acquire(&universe);
+        assert_eq!(universe.reader_count(ty), Some(1));
+        acquire(&universe);
+        assert_eq!(universe.reader_count(ty), Some(2));
+        for _ in 0..2 {
+            universe.objects.lock().unwrap().get_mut(&ty).unwrap().release(Access::Read);
+        }
+        assert_eq!(universe.reader_count(ty), Some(0));
+    }
+    #[test]
+    fn try_register() {
+        let mut universe = Universe::new();
+        universe.try_register::<bobs::Marker>().unwrap();
+        let err = universe.try_register::<bobs::Marker>().unwrap_err();
+        assert!(err.to_string().contains("bobs"), "{}", err);
+        universe.for_each_object(|ty, state, _name| assert_eq!(*state, LockState::Open, "{:?}", ty));
+        universe.eval(|mut bobs: bobs::Write| {
+            bobs.push(bobs::Row { name: "bob", digestion_count: 1 });
+        });
+        assert_eq!(universe.table_len::<bobs::Marker>(), 1);
+    }
+    #[test]
+    fn for_each_object() {
+        let mut universe = Universe::new();
+        bobs::Marker::register(&mut universe);
+        let mut names = vec![];
+        universe.for_each_object(|_ty, state, name| {
+            assert_eq!(*state, LockState::Open);
+            names.push(name);
+        });
+        let count = |what: &str| names.iter().filter(|n| n.starts_with(what)).count();
+        assert_eq!(count("v9::table::TableHeader"), 1);
+        assert_eq!(count("v9::id::IdList<"), 1);
+        assert_eq!(count("v9::column::Column<"), 2);
+    }
+    #[test]
+    fn object_by_name() {
+        let mut universe = Universe::new();
+        assert_eq!(universe.object_by_name("bobs"), None);
+        bobs::Marker::register(&mut universe);
+        let ty = universe.object_by_name("bobs").unwrap();
+        assert_eq!(ty, Ty::of::<bobs::Ids>());
+        let len = universe.with_obj(ty, |ids| ids.downcast_ref::<bobs::Ids>().unwrap().len());
+        assert_eq!(len, 0);
+        assert_eq!(
+            universe.object_by_name("bobs.digestion_count"),
+            Some(Ty::of::<crate::column::Column<bobs::Marker, u64>>()),
+        );
+        assert_eq!(universe.object_by_name("bobs.age"), None);
+    }
 
     #[test]
     #[should_panic]
@@ -601,6 +1271,148 @@ mod test {
         });
     }
 
+    #[test]
+    fn try_ref_row() {
+        let universe = &mut Universe::new();
+        bobs::Marker::register(universe);
+        universe.kmap(|mut bobs: bobs::Write| {
+            for digestion_count in 0..3 {
+                bobs.push(bobs::Row { name: "Bob", digestion_count });
+            }
+            bobs.remove(bobs::Id::new(1));
+        });
+        universe.kmap(|bobs: bobs::Read| {
+            assert_eq!(bobs.try_ref_row(bobs::FIRST).map(|r| *r.digestion_count), Some(0));
+            assert!(bobs.try_ref_row(bobs::Id::new(1)).is_none());
+            assert!(bobs.try_ref_row(bobs::Id::new(7)).is_none());
+        });
+    }
+
+    #[test]
+    fn shrink_to_fit() {
+        let universe = &mut Universe::new();
+        bobs::Marker::register(universe);
+        universe.kmap(|mut bobs: bobs::Write| {
+            for digestion_count in 0..10_000 {
+                bobs.push(bobs::Row { name: "Bob", digestion_count });
+            }
+            assert!(bobs.capacity() >= 10_000);
+        });
+        universe.kmap(|mut bobs: bobs::Write| {
+            bobs.clear();
+        });
+        universe.kmap(|mut bobs: bobs::Write| {
+            bobs.shrink_to_fit();
+            assert!(bobs.capacity() < 10_000);
+            assert_eq!(bobs.ids().outer_capacity(), 0);
+            bobs.push(bobs::Row { name: "Bob", digestion_count: 0 });
+        });
+        universe.kmap(|bobs: bobs::Read| {
+            assert_eq!(bobs.iter().map(|id| id.uncheck().to_usize()).collect::<Vec<_>>(), vec![0]);
+        });
+    }
+
+    #[test]
+    fn snapshot() {
+        let universe = &mut Universe::new();
+        bobs::Marker::register(universe);
+        universe.kmap(|mut bobs: bobs::Write| {
+            for digestion_count in 0..5 {
+                bobs.push(bobs::Row { name: "Bob", digestion_count });
+            }
+        });
+        let dump = |universe: &Universe| universe.eval(|bobs: bobs::Read| {
+            bobs.iter().map(|id| bobs.clone_row(id)).map(|row| (row.name, row.digestion_count)).collect::<Vec<_>>()
+        });
+        let before = dump(universe);
+        let snap = universe.checkpoint_table::<bobs::Marker>();
+        universe.kmap(|mut bobs: bobs::Write| {
+            bobs.remove(bobs::FIRST);
+            bobs.push(bobs::Row { name: "Robert", digestion_count: 100 });
+            bobs.push(bobs::Row { name: "Robert", digestion_count: 101 });
+        });
+        assert_ne!(dump(universe), before);
+        universe.restore_table(snap);
+        assert_eq!(dump(universe), before);
+        universe.kmap(|bobs: bobs::Read| {
+            assert_eq!(bobs.len(), 5);
+            assert_eq!(bobs.ids().outer_capacity(), 5);
+        });
+    }
+
+    #[test]
+    fn zip() {
+        let universe = &mut Universe::new();
+        bobs::Marker::register(universe);
+        universe.kmap(|mut bobs: bobs::Write| {
+            for digestion_count in 0..10 {
+                bobs.push(bobs::Row { name: "Bob", digestion_count });
+            }
+            bobs.remove(bobs::FIRST);
+            bobs.remove(bobs::Id::new(5));
+        });
+        universe.kmap(|bobs: bobs::Read| {
+            let mut manual = vec![];
+            for id in bobs.iter() {
+                manual.push((id.uncheck(), bobs.name[id], bobs.digestion_count[id]));
+            }
+            let zipped: Vec<_> = bobs.zip().map(|(id, row)| (id, *row.name, *row.digestion_count)).collect();
+            assert_eq!(zipped.len(), 8);
+            assert_eq!(zipped, manual);
+        });
+    }
+
+    #[test]
+    fn push_n() {
+        let universe = &mut Universe::new();
+        bobs::Marker::register(universe);
+        universe.kmap(|mut bobs: bobs::Write| {
+            bobs.push(bobs::Row { name: "Bob", digestion_count: 7 });
+            let range = bobs.push_n(100);
+            assert_eq!(range.len(), 100);
+            assert_eq!(bobs.len(), 101);
+            for id in range {
+                assert_eq!(bobs.ref_row(id).digestion_count, &0);
+            }
+        });
+        universe.kmap(|mut bobs: bobs::Edit, ids: &bobs::Ids| {
+            for id in ids.iter().skip(1) {
+                bobs.name[id] = "Robert";
+                bobs.digestion_count[id] = id.to_usize() as u64;
+            }
+        });
+        universe.kmap(|bobs: bobs::Read| {
+            let total: u64 = bobs.iter().map(|id| bobs.digestion_count[id]).sum();
+            assert_eq!(total, 7 + (1..=100).sum::<u64>());
+            assert!(bobs.iter().skip(1).all(|id| bobs.name[id] == "Robert"));
+        });
+    }
+
+    #[test]
+    fn table_len() {
+        use std::sync::mpsc::channel;
+        let universe = &mut Universe::new();
+        bobs::Marker::register(universe);
+        universe.kmap(|mut bobs: bobs::Write| {
+            bobs.push(bobs::Row { name: "Bob", digestion_count: 0 });
+            bobs.push(bobs::Row { name: "Bob", digestion_count: 1 });
+        });
+        let universe = &*universe;
+        let (locked, wait_locked) = channel();
+        let (done, wait_done) = channel::<()>();
+        std::thread::scope(|scope| {
+            scope.spawn(move || {
+                universe.eval(|_: bobs::edit::digestion_count| {
+                    locked.send(()).unwrap();
+                    wait_done.recv().unwrap();
+                });
+            });
+            wait_locked.recv().unwrap();
+            assert_eq!(universe.table_len::<bobs::Marker>(), 2);
+            done.send(()).unwrap();
+        });
+    }
+
     #[test]
     fn separate_col_access() {
         let universe = &mut Universe::new();
@@ -64,6 +64,7 @@ impl<'a, T> Deref for MutButRef<'a, T> {
 
 pub mod die {
     pub static BAD_ITER_LEN: &str = "Iterator must know its exact Id length";
+    pub static ZIP_LEN_MISMATCH: &str = "IdRange and data have different lengths";
 }
 
 pub struct Defer<T: FnMut()>(pub T);
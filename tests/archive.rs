@@ -0,0 +1,70 @@
+use v9::prelude::*;
+
+#[v9::table]
+pub struct orders {
+    pub item: &'static str,
+    pub count: u32,
+}
+
+#[v9::table]
+pub struct old_orders {
+    pub item: &'static str,
+    pub count: u32,
+}
+
+impl From<orders::Row> for old_orders::Row {
+    fn from(row: orders::Row) -> Self {
+        old_orders::Row { item: row.item, count: row.count }
+    }
+}
+
+#[test]
+fn archive() {
+    let mut universe = Universe::new();
+    orders::Marker::register(&mut universe);
+    old_orders::Marker::register(&mut universe);
+    universe.archive_deletes::<orders::Marker, old_orders::Marker>();
+    universe.eval(|mut orders: orders::Write| {
+        orders.push(orders::Row { item: "cheese", count: 3 });
+        orders.push(orders::Row { item: "crackers", count: 1 });
+        orders.push(orders::Row { item: "wine", count: 2 });
+    });
+    universe.eval(|mut orders: orders::Write| {
+        orders.remove(orders::Id::new(0));
+        orders.remove(orders::Id::new(2));
+    });
+    // Recycling the slots mustn't disturb the archive.
+    universe.eval(|mut orders: orders::Write| {
+        orders.push(orders::Row { item: "grapes", count: 9 });
+        orders.push(orders::Row { item: "bread", count: 4 });
+    });
+    universe.eval(|orders: orders::Read, old: old_orders::Read| {
+        assert_eq!(orders.len(), 3);
+        let archived: Vec<_> = old.iter().map(|id| (old.item[id], old.count[id])).collect();
+        assert_eq!(archived, vec![("cheese", 3), ("wine", 2)]);
+    });
+}
+
+#[test]
+fn archive_batched() {
+    let mut universe = Universe::new();
+    orders::Marker::register(&mut universe);
+    old_orders::Marker::register(&mut universe);
+    universe.archive_deletes::<orders::Marker, old_orders::Marker>();
+    universe.eval(|mut orders: orders::Write| {
+        orders.push(orders::Row { item: "cheese", count: 3 });
+        orders.push(orders::Row { item: "crackers", count: 1 });
+    });
+    universe.with_event_batching(|| {
+        universe.eval(|mut orders: orders::Write| orders.remove(orders::Id::new(0)));
+        // This recycles the removed order's slot within the batch.
+        universe.eval(|mut orders: orders::Write| {
+            orders.push(orders::Row { item: "grapes", count: 9 });
+        });
+    });
+    universe.eval(|orders: orders::Read, old: old_orders::Read| {
+        assert_eq!(orders.len(), 2);
+        let archived: Vec<_> = old.iter().map(|id| (old.item[id], old.count[id])).collect();
+        assert_eq!(archived, vec![("cheese", 3)]);
+    });
+}
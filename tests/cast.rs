@@ -0,0 +1,35 @@
+use v9::prelude::*;
+use v9::id::SameIdSpace;
+
+#[v9::table]
+pub struct bodies {
+    pub mass: f32,
+}
+
+#[v9::table]
+pub struct shapes {
+    pub radius: f32,
+}
+
+// Both tables are always pushed to together.
+unsafe impl SameIdSpace<shapes::Marker> for bodies::Marker {}
+
+#[test]
+fn lockstep() {
+    let mut universe = Universe::new();
+    bodies::Marker::register(&mut universe);
+    shapes::Marker::register(&mut universe);
+    universe.eval(|mut bodies: bodies::Write, mut shapes: shapes::Write| {
+        for i in 1..4 {
+            let b = bodies.push(bodies::Row { mass: i as f32 });
+            let s = shapes.push(shapes::Row { radius: i as f32 * 10.0 });
+            assert_eq!(b.cast_marker::<shapes::Marker>(), s);
+        }
+    });
+    universe.eval(|bodies: bodies::Read, shapes: shapes::Read| {
+        for b in bodies.iter() {
+            let s = b.uncheck().cast_marker::<shapes::Marker>();
+            assert_eq!(shapes.radius[s], bodies.mass[b] * 10.0);
+        }
+    });
+}
@@ -0,0 +1,54 @@
+#![cfg(feature = "compressed_column")]
+use v9::prelude::*;
+use v9::column::CompressedColumn;
+use v9::prelude_lib::Ty;
+
+#[v9::table]
+pub struct tiles {
+    pub biome: u8,
+}
+
+type Biomes = CompressedColumn<tiles::Marker, u8>;
+
+#[test]
+fn round_trip() {
+    let mut universe = Universe::new();
+    tiles::Marker::register(&mut universe);
+    universe.eval(|mut tiles: tiles::Write| {
+        for i in 0..3000u32 {
+            tiles.push(tiles::Row { biome: (i / 900) as u8 });
+        }
+    });
+    let compressed = universe.eval(|biome: tiles::read::biome| Biomes::compress(biome.col));
+    assert_eq!(compressed.len(), 3000);
+    assert_eq!(compressed.cached_blocks(), 0);
+    assert!(compressed.memory_bytes() < 1000);
+    universe.eval(|biome: tiles::read::biome| {
+        assert_eq!(compressed.decompress().data(), biome.col.data());
+    });
+}
+
+#[test]
+fn read_in_kernel() {
+    let mut universe = Universe::new();
+    tiles::Marker::register(&mut universe);
+    universe.eval(|mut tiles: tiles::Write| {
+        for i in 0..3000u32 {
+            tiles.push(tiles::Row { biome: (i / 900) as u8 });
+        }
+    });
+    let compressed = universe.eval(|biome: tiles::read::biome| Biomes::compress(biome.col));
+    universe.add_mut(Ty::of::<Biomes>(), compressed);
+    universe.eval(|biomes: &Biomes| {
+        assert_eq!(biomes[tiles::Id::new(899)], 0);
+        assert_eq!(biomes.cached_blocks(), 1);
+        assert_eq!(biomes.get(tiles::Id::new(900)), Some(&1));
+        assert_eq!(biomes.get(tiles::Id::new(2999)), Some(&3));
+        assert_eq!(biomes.get(tiles::Id::new(3000)), None);
+        assert_eq!(biomes.cached_blocks(), 2);
+    });
+    universe.with_mut(|biomes: &mut Biomes| {
+        biomes.evict();
+        assert_eq!(biomes.cached_blocks(), 0);
+    });
+}
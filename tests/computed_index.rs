@@ -0,0 +1,50 @@
+use v9::prelude::*;
+use v9::linkage::ComputedIndex;
+
+#[derive(Debug, Copy, Clone)]
+pub struct Pos {
+    pub x: f32,
+}
+
+#[v9::table]
+pub struct particles {
+    pub pos: crate::Pos,
+}
+
+type Chunks = ComputedIndex<particles::Marker, Pos, i32>;
+
+#[test]
+fn chunks() {
+    let mut universe = Universe::new();
+    particles::Marker::register(&mut universe);
+    universe.add_computed_index::<particles::Marker, Pos, i32, _>(|pos: &Pos| (pos.x / 16.0).floor() as i32);
+    universe.eval(|mut particles: particles::Write| {
+        for &x in &[1.0, 5.0, 17.0, 40.0, -3.0] {
+            particles.push(particles::Row { pos: Pos { x } });
+        }
+    });
+    universe.eval(|chunks: &Chunks| {
+        assert_eq!(chunks.find(0).count(), 2);
+        assert_eq!(chunks.find(1).count(), 1);
+        assert_eq!(chunks.find(2).count(), 1);
+        assert_eq!(chunks.find(-1).count(), 1);
+    });
+    universe.eval(|mut particles: particles::Edit, ids: &particles::Ids| {
+        for id in ids {
+            particles.pos[id].x += 16.0;
+        }
+    });
+    universe.eval(|chunks: &Chunks| {
+        assert_eq!(chunks.find(-1).count(), 0);
+        assert_eq!(chunks.find(0).count(), 1);
+        assert_eq!(chunks.find(1).count(), 2);
+        assert_eq!(chunks.find(3).count(), 1);
+    });
+    universe.eval(|mut particles: particles::Write| {
+        particles.remove(particles::FIRST);
+    });
+    universe.eval(|chunks: &Chunks| {
+        assert_eq!(chunks.find(1).count(), 1);
+        assert_eq!(chunks.map.len(), 4);
+    });
+}
@@ -0,0 +1,40 @@
+use v9::prelude::*;
+
+v9::decl_table! {
+    pub struct cheeses {
+        pub warehouse: crate::warehouses::Id,
+    }
+}
+
+v9::decl_table! {
+    #[dense]
+    pub struct warehouses {
+        pub coordinates: (i32, i32),
+    }
+}
+
+#[test]
+fn swap_remove() {
+    let universe = &mut Universe::new();
+    cheeses::Marker::register(universe);
+    warehouses::Marker::register(universe);
+    universe.kmap(|mut warehouses: warehouses::Write, mut cheeses: cheeses::Write| {
+        for x in 0..4 {
+            let warehouse = warehouses.push(warehouses::Row { coordinates: (x, 0) });
+            cheeses.push(cheeses::Row { warehouse });
+        }
+    });
+    universe.kmap(|mut warehouses: warehouses::Write| {
+        warehouses.remove(warehouses::Id::new(1));
+    });
+    universe.kmap(|warehouses: warehouses::Read, cheeses: cheeses::Read| {
+        assert_eq!(warehouses.ids().outer_capacity(), 3);
+        let xs: Vec<i32> = warehouses.iter().map(|id| warehouses.coordinates[id].0).collect();
+        assert_eq!(xs, vec![0, 3, 2]);
+        assert_eq!(cheeses.len(), 3);
+        for id in cheeses.iter() {
+            let w = cheeses.warehouse[id];
+            assert_eq!(warehouses.coordinates[w].0 as usize, id.uncheck().to_usize());
+        }
+    });
+}
@@ -8,10 +8,56 @@ v9::decl_table! {
     }
 }
 
+v9::decl_table! {
+    struct torches {
+        pub lit: bool,
+    }
+}
+
+v9::decl_table! {
+    struct sconces {
+        pub torch: crate::torches::Id,
+    }
+}
+
+v9::decl_table! {
+    struct members {
+        pub team: u8,
+        pub score: u32,
+    }
+}
+
 v9::decl_property! {
     pub BOMB_PRIMED: ~bool = true;
 }
 
+#[test]
+fn retain() {
+    let mut universe = Universe::new();
+    self::torches::Marker::register(&mut universe);
+    self::BOMB_PRIMED::register(&mut universe);
+    universe.add_tracker_with_ref_arg::<_, _, Delete<self::torches::Marker, lifestage::LOGICAL>>(|ev: KernelArg<&Delete<self::torches::Marker, lifestage::LOGICAL>>, bomb: &mut BOMB_PRIMED| {
+        let runs: Vec<(u32, u32)> = ev.ids.iter_runs_inclusive().map(|r| (r.start().0, r.end().0)).collect();
+        assert_eq!(runs, vec![(2, 5), (8, 8)]);
+        **bomb = false;
+    });
+    universe.eval(|mut torches: self::torches::Write| {
+        for i in 0..10 {
+            torches.push(self::torches::Row { lit: (2..6).contains(&i) || i == 8 });
+        }
+    });
+    universe.eval(|mut torches: self::torches::Write| {
+        torches.retain(|_id, row| !*row.lit);
+    });
+    universe.with(|bomb: &BOMB_PRIMED| {
+        assert!(!**bomb);
+    });
+    universe.eval(|torches: self::torches::Read| {
+        assert_eq!(torches.len(), 5);
+        assert!(torches.iter().all(|id| !torches.lit[id]));
+    });
+}
+
 #[test]
 fn track_edit() {
     let mut universe = Universe::new();
@@ -44,6 +90,99 @@ fn track_edit() {
     });
 }
 
+#[test]
+fn edit_unordered() {
+    let mut universe = Universe::new();
+    self::dudes::Marker::register(&mut universe);
+    self::BOMB_PRIMED::register(&mut universe);
+    universe.add_tracker_with_ref_arg::<_, _, Edit<self::dudes::Marker, u64>>(|ev: KernelArg<&Edit<self::dudes::Marker, u64>>, bomb: &mut BOMB_PRIMED| {
+        let changes: Vec<(u32, u64, u64)> = ev.iter_changes().map(|(id, old, new)| (id.0, *old, *new)).collect();
+        assert_eq!(changes, vec![(0, 0, 10), (1, 1, 12), (3, 3, 30)]);
+        **bomb = false;
+    });
+    universe.eval(|mut dudes: self::dudes::Write| {
+        for dudeitude in 0..4 {
+            dudes.push(self::dudes::Row { dudeitude });
+        }
+    });
+    universe.eval(|mut dudes: self::dudes::Edit| {
+        let id = self::dudes::Id::new;
+        dudes.dudeitude[id(1)] = 11;
+        dudes.dudeitude.edit_unordered(id(3), |d| *d = 30);
+        dudes.dudeitude.edit_unordered(id(0), |d| *d = 10);
+        dudes.dudeitude.edit_unordered(id(1), |d| *d += 1);
+        assert_eq!(dudes.dudeitude[id(1)], 12);
+        assert_eq!(dudes.dudeitude[id(2)], 2);
+    });
+    universe.with(|bomb: &BOMB_PRIMED| {
+        assert!(!**bomb);
+    });
+    universe.eval(|dudes: self::dudes::Read| {
+        let all: Vec<u64> = dudes.iter().map(|id| dudes.dudeitude[id]).collect();
+        assert_eq!(all, vec![10, 12, 2, 30]);
+    });
+}
+
+#[test]
+fn edit_changed_only() {
+    let mut universe = Universe::new();
+    self::dudes::Marker::register(&mut universe);
+    self::BOMB_PRIMED::register(&mut universe);
+    universe.add_tracker_with_ref_arg::<_, _, Edit<self::dudes::Marker, u64>>(|ev: KernelArg<&Edit<self::dudes::Marker, u64>>, bomb: &mut BOMB_PRIMED| {
+        assert_eq!(ev.iter_changes().count(), 3);
+        let changed: Vec<(u32, u64, u64)> = ev.iter_changed().map(|(id, old, new)| (id.0, *old, *new)).collect();
+        assert_eq!(changed, vec![(0, 0, 5), (2, 2, 7)]);
+        **bomb = false;
+    });
+    universe.eval(|mut dudes: self::dudes::Write| {
+        for dudeitude in 0..3 {
+            dudes.push(self::dudes::Row { dudeitude });
+        }
+    });
+    universe.eval(|mut dudes: self::dudes::Edit| {
+        let id = self::dudes::Id::new;
+        dudes.dudeitude[id(0)] = 5;
+        dudes.dudeitude[id(1)] += 0;
+        dudes.dudeitude[id(2)] = 7;
+    });
+    universe.with(|bomb: &BOMB_PRIMED| {
+        assert!(!**bomb);
+    });
+}
+
+#[test]
+fn edit_old_values() {
+    let mut universe = Universe::new();
+    self::dudes::Marker::register(&mut universe);
+    self::BOMB_PRIMED::register(&mut universe);
+    universe.add_tracker_with_ref_arg::<_, _, Edit<self::dudes::Marker, u64>>(|ev: KernelArg<&Edit<self::dudes::Marker, u64>>, bomb: &mut BOMB_PRIMED| {
+        let mut n = 0;
+        for (id, old, new) in ev.iter_changes() {
+            assert_eq!(*old, 10);
+            assert_eq!(*new, 100);
+            assert_eq!(ev.old(id), old);
+            n += 1;
+        }
+        assert_eq!(n, 2);
+        **bomb = false;
+    });
+    universe.eval(|mut dudes: self::dudes::Write| {
+        dudes.push(self::dudes::Row {
+            dudeitude: 10,
+        });
+        dudes.push(self::dudes::Row {
+            dudeitude: 10,
+        });
+    });
+    universe.eval(|mut dudes: self::dudes::Edit, iter: &self::dudes::Ids| {
+        for dude in iter {
+            dudes.dudeitude[dude] = 100;
+        }
+    });
+    universe.with(|bomb: &BOMB_PRIMED| {
+        assert!(!**bomb);
+    });
+}
 
 #[test]
 fn track_removal() {
@@ -110,3 +249,382 @@ fn track_removal() {
     check_defused(false);
     println!("Better than Shakespeare. Fight me.");
 }
+
+#[test]
+fn clear_fast() {
+    let mut universe = Universe::new();
+    self::dudes::Marker::register(&mut universe);
+    self::BOMB_PRIMED::register(&mut universe);
+    universe.add_index::<self::dudes::Marker, u64>();
+    universe.add_tracker_with_ref_arg::<_, _, Delete<self::dudes::Marker, lifestage::MEMORY>>(|_ev: KernelArg<&Delete<self::dudes::Marker, lifestage::MEMORY>>| {
+        panic!("clear_fast sent a Delete");
+    });
+    universe.add_tracker_with_ref_arg::<_, _, Cleared<self::dudes::Marker>>(|ev: KernelArg<&Cleared<self::dudes::Marker>>, bomb: &mut BOMB_PRIMED| {
+        assert_eq!(ev.ids.len(), 100);
+        assert_eq!(ev.ids.iter_runs().count(), 1);
+        **bomb = false;
+    });
+    universe.eval(|mut dudes: self::dudes::Write| {
+        for dudeitude in 0..100 {
+            dudes.push(self::dudes::Row { dudeitude });
+        }
+    });
+    universe.eval(|index: &ColumnIndex<self::dudes::Marker, u64>| {
+        assert_eq!(index.map.len(), 100);
+    });
+    universe.eval(|mut dudes: self::dudes::Write| {
+        dudes.clear_fast();
+    });
+    universe.with(|bomb: &BOMB_PRIMED| {
+        assert!(!**bomb);
+    });
+    universe.eval(|index: &ColumnIndex<self::dudes::Marker, u64>, dudes: self::dudes::Read| {
+        assert!(index.map.is_empty());
+        assert_eq!(dudes.len(), 0);
+        assert_eq!(dudes.ids().outer_capacity(), 0);
+        assert!(dudes.dudeitude.col.data().is_empty());
+    });
+    universe.eval(|mut dudes: self::dudes::Write| {
+        let id = dudes.push(self::dudes::Row { dudeitude: 7 });
+        assert_eq!(id, self::dudes::FIRST);
+    });
+}
+
+#[test]
+fn push_tracked() {
+    let mut universe = Universe::new();
+    self::dudes::Marker::register(&mut universe);
+    universe.add_index::<self::dudes::Marker, u64>();
+    universe.eval(|universe: UniverseRef| {
+        let id = self::dudes::push_tracked(&universe, self::dudes::Row { dudeitude: 42 });
+        universe.eval(|index: &ColumnIndex<self::dudes::Marker, u64>| {
+            assert_eq!(index.find(42).collect::<Vec<_>>(), vec![id]);
+        });
+    });
+}
+
+#[test]
+fn indexed_column() {
+    let mut universe = Universe::new();
+    self::dudes::Marker::register(&mut universe);
+    universe.add_index::<self::dudes::Marker, u64>();
+    universe.eval(|mut dudes: self::dudes::Write| {
+        for dudeitude in &[3, 7, 3, 9] {
+            dudes.push(self::dudes::Row { dudeitude: *dudeitude });
+        }
+    });
+    universe.eval(|dudes: IndexedColumn<self::dudes::Marker, u64>| {
+        let threes: Vec<(u32, u64)> = dudes.find(3).map(|(id, &v)| (id.0, v)).collect();
+        assert_eq!(threes, vec![(0, 3), (2, 3)]);
+        assert_eq!(dudes.find(4).count(), 0);
+    });
+}
+
+#[test]
+fn clear_fast_cascades() {
+    let mut universe = Universe::new();
+    self::torches::Marker::register(&mut universe);
+    self::sconces::Marker::register(&mut universe);
+    universe.eval(|mut torches: self::torches::Write, mut sconces: self::sconces::Write| {
+        for _ in 0..3 {
+            let torch = torches.push(self::torches::Row { lit: true });
+            sconces.push(self::sconces::Row { torch });
+        }
+    });
+    universe.eval(|mut torches: self::torches::Write| torches.clear_fast());
+    universe.eval(|torches: self::torches::Read, sconces: self::sconces::Read| {
+        assert_eq!(torches.len(), 0);
+        assert_eq!(sconces.len(), 0);
+    });
+}
+
+#[test]
+fn retain_recycled() {
+    let mut universe = Universe::new();
+    self::torches::Marker::register(&mut universe);
+    universe.eval(|mut torches: self::torches::Write| {
+        for _ in 0..6 {
+            torches.push(self::torches::Row { lit: false });
+        }
+    });
+    universe.eval(|mut torches: self::torches::Write| {
+        torches.remove(self::torches::Id::new(4));
+        torches.remove(self::torches::Id::new(1));
+    });
+    universe.eval(|mut torches: self::torches::Write| {
+        for _ in 0..2 {
+            torches.push(self::torches::Row { lit: true });
+        }
+    });
+    universe.eval(|mut torches: self::torches::Write| {
+        torches.retain(|_id, row| !*row.lit);
+    });
+    universe.eval(|torches: self::torches::Read| {
+        assert_eq!(torches.len(), 4);
+        assert!(torches.iter().all(|id| !torches.lit[id]));
+    });
+}
+
+#[test]
+fn clear_fast_recycled() {
+    let mut universe = Universe::new();
+    self::torches::Marker::register(&mut universe);
+    universe.eval(|mut torches: self::torches::Write| {
+        for _ in 0..6 {
+            torches.push(self::torches::Row { lit: true });
+        }
+    });
+    universe.eval(|mut torches: self::torches::Write| {
+        torches.remove(self::torches::Id::new(4));
+        torches.remove(self::torches::Id::new(1));
+    });
+    universe.eval(|mut torches: self::torches::Write| {
+        for _ in 0..3 {
+            torches.push(self::torches::Row { lit: false });
+        }
+    });
+    universe.eval(|mut torches: self::torches::Write| torches.clear_fast());
+    universe.eval(|torches: self::torches::Read| {
+        assert_eq!(torches.len(), 0);
+        assert!(torches.lit.col.data().is_empty());
+    });
+}
+
+#[test]
+fn reset() {
+    let mut universe = Universe::new();
+    self::torches::Marker::register(&mut universe);
+    self::sconces::Marker::register(&mut universe);
+    universe.eval(|mut torches: self::torches::Write, mut sconces: self::sconces::Write| {
+        for _ in 0..3 {
+            let torch = torches.push(self::torches::Row { lit: true });
+            sconces.push(self::sconces::Row { torch });
+        }
+        torches.remove(self::torches::Id::new(0));
+    });
+    universe.eval(|mut torches: self::torches::Write| torches.reset());
+    universe.eval(|torches: self::torches::Read, sconces: self::sconces::Read| {
+        assert_eq!(sconces.len(), 0);
+        assert_eq!(torches.ids().outer_capacity(), 0);
+        assert!(torches.lit.col.data().is_empty());
+    });
+    universe.eval(|mut torches: self::torches::Write| {
+        let id = torches.push(self::torches::Row { lit: false });
+        assert_eq!(id, self::torches::FIRST);
+    });
+}
+
+#[test]
+fn reset_recycled() {
+    let mut universe = Universe::new();
+    self::torches::Marker::register(&mut universe);
+    universe.eval(|mut torches: self::torches::Write| {
+        for _ in 0..6 {
+            torches.push(self::torches::Row { lit: true });
+        }
+    });
+    universe.eval(|mut torches: self::torches::Write| {
+        torches.remove(self::torches::Id::new(4));
+        torches.remove(self::torches::Id::new(1));
+    });
+    universe.eval(|mut torches: self::torches::Write| {
+        for _ in 0..3 {
+            torches.push(self::torches::Row { lit: false });
+        }
+    });
+    universe.eval(|mut torches: self::torches::Write| torches.reset());
+    universe.eval(|torches: self::torches::Read| {
+        assert_eq!(torches.len(), 0);
+        assert_eq!(torches.ids().outer_capacity(), 0);
+    });
+    universe.eval(|mut torches: self::torches::Write| {
+        let id = torches.push(self::torches::Row { lit: false });
+        assert_eq!(id, self::torches::FIRST);
+    });
+}
+
+#[test]
+fn event_batching() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static PUSHES: AtomicUsize = AtomicUsize::new(0);
+    let mut universe = Universe::new();
+    self::dudes::Marker::register(&mut universe);
+    universe.add_tracker_with_ref_arg::<_, _, Push<self::dudes::Marker, lifestage::LOGICAL>>(|ev: KernelArg<&Push<self::dudes::Marker, lifestage::LOGICAL>>| {
+        assert_eq!(ev.ids.len(), 3);
+        PUSHES.fetch_add(1, Ordering::SeqCst);
+    });
+    universe.with_event_batching(|| {
+        universe.eval(|mut dudes: self::dudes::Write| {
+            dudes.push(self::dudes::Row { dudeitude: 1 });
+            dudes.push(self::dudes::Row { dudeitude: 2 });
+        });
+        universe.eval(|mut dudes: self::dudes::Write| {
+            dudes.push(self::dudes::Row { dudeitude: 3 });
+            dudes.push(self::dudes::Row { dudeitude: 4 });
+        });
+        universe.eval(|mut dudes: self::dudes::Write| {
+            dudes.remove(self::dudes::FIRST);
+        });
+        assert_eq!(PUSHES.load(Ordering::SeqCst), 0);
+    });
+    assert_eq!(PUSHES.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn capacity_limit() {
+    let mut universe = Universe::new();
+    self::dudes::Marker::register(&mut universe);
+    universe.set_capacity_limit::<self::dudes::Marker>(2);
+    universe.eval(|mut dudes: self::dudes::Write| {
+        dudes.push(self::dudes::Row { dudeitude: 1 });
+        dudes.push(self::dudes::Row { dudeitude: 2 });
+    });
+    universe.eval(|mut dudes: self::dudes::Write| dudes.remove(self::dudes::FIRST));
+    universe.eval(|mut dudes: self::dudes::Write| {
+        dudes.push(self::dudes::Row { dudeitude: 3 });
+    });
+}
+
+#[test]
+#[should_panic(expected = "table dudes has 3 rows, which is over its limit of 2")]
+fn capacity_limit_exceeded() {
+    let mut universe = Universe::new();
+    self::dudes::Marker::register(&mut universe);
+    universe.set_capacity_limit::<self::dudes::Marker>(2);
+    universe.eval(|mut dudes: self::dudes::Write| {
+        for i in 0..3 {
+            dudes.push(self::dudes::Row { dudeitude: i });
+        }
+    });
+}
+
+#[test]
+fn row_exists() {
+    let mut universe = Universe::new();
+    self::dudes::Marker::register(&mut universe);
+    let (a, b) = universe.eval(|mut dudes: self::dudes::Write| {
+        (dudes.push(self::dudes::Row { dudeitude: 1 }), dudes.push(self::dudes::Row { dudeitude: 2 }))
+    });
+    assert!(universe.row_exists(a));
+    assert!(universe.row_exists(b));
+    universe.eval(|mut dudes: self::dudes::Write| dudes.remove(a));
+    assert!(!universe.row_exists(a));
+    assert!(universe.row_exists(b));
+    assert!(!universe.row_exists(self::dudes::Id::new(7)));
+}
+
+#[test]
+fn manual_selection() {
+    use v9::linkage::Selection;
+    let mut universe = Universe::new();
+    self::dudes::Marker::register(&mut universe);
+    self::torches::Marker::register(&mut universe);
+    self::BOMB_PRIMED::register(&mut universe);
+    universe.add_tracker_with_ref_arg::<_, _, Selection>(|ev: KernelArg<&Selection>, bomb: &mut BOMB_PRIMED| {
+        assert!(ev.get::<self::dudes::Marker>().is_some());
+        **bomb = false;
+    });
+    let mut selection = Selection::default();
+    selection.insert_ids(RunList::on(self::dudes::Id::new(1)));
+    selection.insert_ids(RunList::on(self::dudes::Id::new(3)));
+    selection.insert_ids(RunList::on(self::torches::FIRST));
+    let selection = universe.select(selection);
+    universe.with(|bomb: &BOMB_PRIMED| assert!(!**bomb));
+    let dudes: Vec<_> = selection.get::<self::dudes::Marker>().unwrap().iter().collect();
+    assert_eq!(dudes, vec![self::dudes::Id::new(1), self::dudes::Id::new(3)]);
+    assert_eq!(selection.get::<self::torches::Marker>().unwrap().len(), 1);
+    assert_eq!(selection.selection_order.len(), 2);
+}
+
+#[test]
+fn derived_index() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use v9::linkage::DerivedIndex;
+    static RECOMPUTES: AtomicUsize = AtomicUsize::new(0);
+    type M = self::members::Marker;
+    let mut universe = Universe::new();
+    M::register(&mut universe);
+    universe.add_derived_index::<M, u8, u32>(|universe, &team| {
+        RECOMPUTES.fetch_add(1, Ordering::SeqCst);
+        universe.eval(|members: self::members::Read| {
+            members.iter().filter(|&id| members.team[id] == team).map(|id| members.score[id]).sum::<u32>()
+        })
+    });
+    universe.add_derived_dependency::<M, u8, u32, u32>();
+    let (alice, _bob, _carol) = universe.eval(|mut members: self::members::Write| {
+        (
+            members.push(self::members::Row { team: 1, score: 10 }),
+            members.push(self::members::Row { team: 1, score: 5 }),
+            members.push(self::members::Row { team: 2, score: 7 }),
+        )
+    });
+    let total = |team| universe.derived::<M, u8, u32>(&team);
+    assert_eq!((total(1), total(2)), (15, 7));
+    assert_eq!((total(1), total(2)), (15, 7));
+    assert_eq!(RECOMPUTES.load(Ordering::SeqCst), 2);
+    universe.eval(|mut members: self::members::Edit| {
+        members.score[alice] = 20;
+    });
+    universe.with(|index: &DerivedIndex<M, u8, u32>| {
+        assert!(!index.cache.contains_key(&1));
+        assert!(index.cache.contains_key(&2));
+    });
+    assert_eq!((total(1), total(2)), (25, 7));
+    assert_eq!(RECOMPUTES.load(Ordering::SeqCst), 3);
+    universe.eval(|mut members: self::members::Edit| {
+        members.team[alice] = 2;
+    });
+    assert_eq!((total(1), total(2)), (5, 27));
+    assert_eq!(RECOMPUTES.load(Ordering::SeqCst), 5);
+}
+
+#[test]
+fn event_batching_deletes() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static DELETES: AtomicUsize = AtomicUsize::new(0);
+    let mut universe = Universe::new();
+    self::dudes::Marker::register(&mut universe);
+    universe.add_column_with_default::<self::dudes::Marker, i8>(7);
+    universe.add_tracker_with_ref_arg::<_, _, Delete<self::dudes::Marker, lifestage::LOGICAL>>(|ev: KernelArg<&Delete<self::dudes::Marker, lifestage::LOGICAL>>| {
+        assert_eq!(ev.ids.len(), 1);
+        DELETES.fetch_add(1, Ordering::SeqCst);
+    });
+    let ids = universe.eval(|mut dudes: self::dudes::Write| {
+        (0..3).map(|dudeitude| dudes.push(self::dudes::Row { dudeitude })).collect::<Vec<_>>()
+    });
+    universe.with_event_batching(|| {
+        universe.eval(|mut dudes: self::dudes::Write| dudes.remove(ids[0]));
+        // Deletes aren't held back.
+        assert_eq!(DELETES.load(Ordering::SeqCst), 1);
+        universe.eval(|mut dudes: self::dudes::Write| dudes.remove(ids[1]));
+        // Columns still hear about new rows right away.
+        let id = universe.eval(|mut dudes: self::dudes::Write| dudes.push(self::dudes::Row { dudeitude: 9 }));
+        universe.eval(|extra: v9::column::ReadColumn<self::dudes::Marker, i8>| assert_eq!(extra[id], 7));
+        // A row pushed & removed within the batch isn't mentioned.
+        universe.eval(|mut dudes: self::dudes::Write| dudes.remove(id));
+    });
+    assert_eq!(DELETES.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn event_batching_panic() {
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static PUSHED: AtomicUsize = AtomicUsize::new(0);
+    let mut universe = Universe::new();
+    self::dudes::Marker::register(&mut universe);
+    universe.add_tracker_with_ref_arg::<_, _, Push<self::dudes::Marker, lifestage::LOGICAL>>(|ev: KernelArg<&Push<self::dudes::Marker, lifestage::LOGICAL>>| {
+        PUSHED.fetch_add(ev.ids.len(), Ordering::SeqCst);
+    });
+    let r = panic::catch_unwind(AssertUnwindSafe(|| {
+        universe.with_event_batching(|| {
+            universe.eval(|mut dudes: self::dudes::Write| dudes.push(self::dudes::Row { dudeitude: 1 }));
+            panic!("batch interrupted");
+        })
+    }));
+    assert!(r.is_err());
+    assert_eq!(PUSHED.load(Ordering::SeqCst), 0);
+    // The batch is over, so this is sent right away, after the row left over from the batch.
+    universe.eval(|mut dudes: self::dudes::Write| dudes.push(self::dudes::Row { dudeitude: 2 }));
+    assert_eq!(PUSHED.load(Ordering::SeqCst), 2);
+}
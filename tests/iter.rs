@@ -17,3 +17,165 @@ fn main() {
         }
     });
 }
+
+#[test]
+fn sorted_iter() {
+    let universe = &mut Universe::new();
+    my_table::Marker::register(universe);
+    universe.kmap(|mut t: my_table::Write| {
+        for i in 0..20 {
+            t.push(my_table::Row { names: format!("#{}", i), age: i as f64 });
+        }
+    });
+    universe.kmap(|mut t: my_table::Write| {
+        for i in &[15, 3, 9, 4, 17] {
+            t.remove(my_table::Id::new(*i));
+        }
+    });
+    universe.kmap(|mut t: my_table::Write| {
+        // Recycles some of the holes.
+        for i in 0..3 {
+            t.push(my_table::Row { names: format!("recycled #{}", i), age: -1.0 });
+        }
+    });
+    universe.kmap(|ids: &my_table::Ids| {
+        let sorted: Vec<usize> = ids.sorted_iter().map(|id| id.to_usize()).collect();
+        assert_eq!(sorted.len(), 18);
+        assert!(sorted.windows(2).all(|w| w[0] < w[1]));
+        let mut all: Vec<usize> = ids.iter().map(|id| id.to_usize()).collect();
+        all.sort();
+        assert_eq!(sorted, all);
+    });
+}
+
+#[test]
+fn map_in_place() {
+    type Ages<'a> = v9::column::FastEditColumn<'a, my_table::Marker, f64>;
+    let universe = &mut Universe::new();
+    my_table::Marker::register(universe);
+    universe.kmap(|mut t: my_table::Write| {
+        for i in 0..5 {
+            t.push(my_table::Row { names: format!("#{}", i), age: i as f64 });
+        }
+    });
+    universe.kmap(|mut ages: Ages| {
+        ages.map_in_place(|_id, age| *age *= 2.0);
+    });
+    universe.kmap(|ages: my_table::read::age| {
+        let all: Vec<f64> = ages.iter().map(|(_id, age)| *age).collect();
+        assert_eq!(all, vec![0.0, 2.0, 4.0, 6.0, 8.0]);
+    });
+    universe.kmap(|mut ages: Ages| {
+        ages.fill(1.5);
+    });
+    universe.kmap(|ages: my_table::read::age| {
+        assert!(ages.iter().all(|(_id, age)| *age == 1.5));
+    });
+}
+
+#[test]
+fn get_out_of_bounds() {
+    type Ages<'a> = v9::column::FastEditColumn<'a, my_table::Marker, f64>;
+    let universe = &mut Universe::new();
+    my_table::Marker::register(universe);
+    universe.kmap(|mut t: my_table::Write| {
+        t.push(my_table::Row { names: format!("#0"), age: 3.0 });
+    });
+    let stale = my_table::Id::new(7);
+    universe.kmap(|ages: my_table::read::age| {
+        assert_eq!(ages.get(my_table::FIRST), Some(&3.0));
+        assert_eq!(ages.get(stale), None);
+    });
+    universe.kmap(|mut ages: Ages| {
+        *ages.get_mut(my_table::FIRST).unwrap() += 1.0;
+        assert!(ages.get_mut(stale).is_none());
+        assert_eq!(ages.get(my_table::FIRST), Some(&4.0));
+    });
+}
+
+#[test]
+fn removing_rows() {
+    let universe = &mut Universe::new();
+    my_table::Marker::register(universe);
+    universe.kmap(|mut t: my_table::Write| {
+        for i in 0..10 {
+            t.push(my_table::Row { names: format!("#{}", i), age: i as f64 });
+        }
+    });
+    universe.kmap(|mut t: my_table::Write| {
+        for rm in t.removing_rows() {
+            if *rm.row.age >= 5.0 || rm.row.names == "#1" {
+                rm.remove();
+            }
+        }
+    });
+    universe.kmap(|t: my_table::Read| {
+        let names: Vec<&str> = t.iter().map(|id| t.names[id].as_str()).collect();
+        assert_eq!(names, vec!["#0", "#2", "#3", "#4"]);
+    });
+}
+
+v9::decl_table! {
+    pub struct switches {
+        pub on: bool,
+    }
+}
+
+#[test]
+fn group_by() {
+    let universe = &mut Universe::new();
+    switches::Marker::register(universe);
+    universe.kmap(|mut t: switches::Write| {
+        for i in 0..6 {
+            t.push(switches::Row { on: i % 3 == 0 });
+        }
+        t.remove(switches::Id::new(4));
+    });
+    universe.kmap(|on: switches::read::on, ids: &switches::Ids| {
+        let groups = on.group_by(ids);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[&true], vec![switches::Id::new(0), switches::Id::new(3)]);
+        assert_eq!(groups[&false], vec![switches::Id::new(1), switches::Id::new(2), switches::Id::new(5)]);
+    });
+}
+
+#[test]
+fn extend_column() {
+    type Ages<'a> = v9::column::WriteColumn<'a, my_table::Marker, f64>;
+    let universe = &mut Universe::new();
+    my_table::Marker::register(universe);
+    universe.kmap(|mut t: my_table::Write| {
+        t.push(my_table::Row { names: format!("#0"), age: 3.0 });
+    });
+    universe.kmap(|mut ages: Ages| {
+        assert_eq!(ages.extend_from(vec![4.0, 5.0].into_iter()), 2);
+        assert_eq!(ages[my_table::FIRST], 3.0);
+    });
+    universe.kmap(|ages: my_table::read::age| {
+        let ages: Vec<f64> = ages.iter().map(|(_id, age)| *age).collect();
+        assert_eq!(ages, vec![3.0, 4.0, 5.0]);
+    });
+}
+
+#[test]
+fn column_slices() {
+    type Ages<'a> = v9::column::FastEditColumn<'a, my_table::Marker, f64>;
+    let universe = &mut Universe::new();
+    my_table::Marker::register(universe);
+    universe.kmap(|mut t: my_table::Write| {
+        for i in 0..8 {
+            t.push(my_table::Row { names: format!("#{}", i), age: i as f64 });
+        }
+    });
+    universe.kmap(|mut ages: Ages| {
+        for age in ages.as_mut_slice() {
+            *age *= 2.0;
+        }
+    });
+    universe.kmap(|ages: my_table::read::age, ids: &my_table::Ids| {
+        let fast: f64 = ages.as_slice().iter().sum();
+        let slow: f64 = ids.iter().map(|id| ages[id]).sum();
+        assert_eq!(fast, slow);
+        assert_eq!(fast, 56.0);
+    });
+}
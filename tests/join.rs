@@ -0,0 +1,96 @@
+//! The canonical "update A from B" join.
+//!
+//! A kernel may take any number of tables, so long as it doesn't ask for the same column twice.
+//! The supported shapes are:
+//! - `a::Edit` + `&a::Ids` + `b::Read`: change existing rows of A based on B.
+//! - `a::Write` + `b::Read`: push rows to A based on B.
+//! - `a::Read` + `b::Edit` + `&b::Ids`: the reverse.
+//! Asking for `a::Edit` and `a::Read` together (or `a::Write` and `a::Edit`) conflicts, and
+//! panics when the kernel is built.
+
+use v9::prelude::*;
+
+#[v9::table]
+pub struct cheeses {
+    pub quantity: f64,
+    pub warehouse: crate::warehouses::Id,
+}
+
+#[v9::table]
+pub struct warehouses {
+    pub on_fire: bool,
+    pub temperature: f32,
+}
+
+fn setup() -> Universe {
+    let mut universe = Universe::new();
+    cheeses::Marker::register(&mut universe);
+    warehouses::Marker::register(&mut universe);
+    universe.eval(|mut warehouses: warehouses::Write, mut cheeses: cheeses::Write| {
+        let cold = warehouses.push(warehouses::Row { on_fire: false, temperature: 4.0 });
+        let hot = warehouses.push(warehouses::Row { on_fire: true, temperature: 900.0 });
+        for &warehouse in &[cold, hot, cold, hot] {
+            cheeses.push(cheeses::Row { quantity: 10.0, warehouse });
+        }
+    });
+    universe
+}
+
+#[test]
+fn update_a_from_b() {
+    let universe = setup();
+    universe.eval(|mut cheeses: cheeses::Edit, cheese_ids: &cheeses::Ids, warehouses: warehouses::Read| {
+        for id in cheese_ids {
+            let warehouse = cheeses.warehouse[id];
+            if warehouses.on_fire[warehouse] {
+                cheeses.quantity[id] = 0.0;
+            }
+        }
+    });
+    universe.eval(|cheeses: cheeses::Read, warehouses: warehouses::Read| {
+        for id in cheeses.iter() {
+            let burnt = warehouses.on_fire[cheeses.warehouse[id]];
+            assert_eq!(cheeses.quantity[id], if burnt { 0.0 } else { 10.0 });
+        }
+    });
+}
+
+#[test]
+fn update_b_from_a() {
+    let universe = setup();
+    universe.eval(|cheeses: cheeses::Read, mut warehouses: warehouses::Edit, warehouse_ids: &warehouses::Ids| {
+        for wid in warehouse_ids {
+            let stock = cheeses
+                .iter()
+                .filter(|&cid| cheeses.warehouse[cid] == wid.uncheck())
+                .count();
+            warehouses.temperature[wid] -= stock as f32;
+        }
+    });
+    universe.eval(|warehouses: warehouses::Read| {
+        let temps: Vec<f32> = warehouses.iter().map(|id| warehouses.temperature[id]).collect();
+        assert_eq!(temps, vec![2.0, 898.0]);
+    });
+}
+
+#[test]
+fn push_a_from_b() {
+    let universe = setup();
+    universe.eval(|mut cheeses: cheeses::Write, warehouses: warehouses::Read| {
+        for wid in warehouses.iter() {
+            if !warehouses.on_fire[wid] {
+                cheeses.push(cheeses::Row { quantity: 1.0, warehouse: wid.uncheck() });
+            }
+        }
+    });
+    universe.eval(|cheeses: cheeses::Read| {
+        assert_eq!(cheeses.len(), 5);
+    });
+}
+
+#[test]
+#[should_panic(expected = "conflicting acquisitions")]
+fn same_table_twice() {
+    let universe = setup();
+    universe.eval(|_: cheeses::Edit, _: cheeses::Read| {});
+}
@@ -31,3 +31,419 @@ fn borrowing_universe() {
     owo.eval(|_this: UniverseRef| {
     });
 }
+
+v9::decl_property! { COUNTER: ~u32 }
+
+#[test]
+fn readonly() {
+    let mut u = Universe::new();
+    COUNTER::register(&mut u);
+    let mut k = Kernel::new(|c: &COUNTER| {
+        assert_eq!(**c, 0);
+    });
+    assert!(k.is_readonly());
+    u.run_readonly(&mut k);
+}
+
+#[test]
+#[should_panic(expected = "run_readonly")]
+fn readonly_rejects_writes() {
+    let mut u = Universe::new();
+    COUNTER::register(&mut u);
+    let mut k = Kernel::new(|c: &mut COUNTER| {
+        **c += 1;
+    });
+    assert!(!k.is_readonly());
+    u.run_readonly(&mut k);
+}
+
+#[test]
+fn lock_upgrade() {
+    let mut lock = Locked::new(Box::new(0u32), "u32");
+    lock.acquire(Access::Read);
+    assert!(lock.try_upgrade());
+    unsafe {
+        let obj: &mut dyn AnyDebug = &mut *lock.contents();
+        *obj.downcast_mut::<u32>().unwrap() = 5;
+    }
+    lock.downgrade();
+    lock.acquire(Access::Read);
+    // Somebody else is reading too.
+    assert!(!lock.try_upgrade());
+    lock.release(Access::Read);
+    lock.release(Access::Read);
+    assert_eq!(lock.state, LockState::Open);
+}
+
+#[test]
+fn upgrade_with() {
+    let mut u = Universe::new();
+    COUNTER::register(&mut u);
+    u.eval(|c: &COUNTER, universe: UniverseRef| {
+        assert_eq!(**c, 0);
+        let done = unsafe { universe.upgrade_with(|c: &mut COUNTER| **c += 1) };
+        assert_eq!(done, Some(()));
+    });
+    u.eval(|c: &mut COUNTER| {
+        assert_eq!(**c, 1);
+    });
+}
+
+#[test]
+fn named() {
+    let mut k = Kernel::named("bump counter", |c: &mut COUNTER| {
+        **c += 1;
+    });
+    assert_eq!(k.name.name, "bump counter");
+    assert!(format!("{:?}", k).starts_with("bump counter at "));
+    k.set_name(format!("bump counter #{}", 2));
+    assert_eq!(k.name.name, "bump counter #2");
+}
+
+#[test]
+#[should_panic(expected = "kernel doomed at")]
+fn named_in_panic() {
+    let u = Universe::new();
+    let mut k = Kernel::named("doomed", |_c: &COUNTER| {});
+    // COUNTER isn't registered.
+    u.run(&mut k);
+}
+
+#[test]
+fn run_dyn() {
+    let mut u = Universe::new();
+    COUNTER::register(&mut u);
+    let resources = [(Ty::of::<COUNTER>(), Access::Write)];
+    for _ in 0..3 {
+        u.run_dyn(&resources, &mut |mut rez: Rez, _u: &Universe| {
+            let c: &mut COUNTER = unsafe { rez.take_mut_downcast() };
+            **c += 1;
+        });
+    }
+    u.eval(|c: &COUNTER| assert_eq!(**c, 3));
+}
+
+v9::decl_property! { P00: ~u32 }
+v9::decl_property! { P01: ~u32 }
+v9::decl_property! { P02: ~u32 }
+v9::decl_property! { P03: ~u32 }
+v9::decl_property! { P04: ~u32 }
+v9::decl_property! { P05: ~u32 }
+v9::decl_property! { P06: ~u32 }
+v9::decl_property! { P07: ~u32 }
+v9::decl_property! { P08: ~u32 }
+v9::decl_property! { P09: ~u32 }
+v9::decl_property! { P10: ~u32 }
+v9::decl_property! { P11: ~u32 }
+v9::decl_property! { P12: ~u32 }
+v9::decl_property! { P13: ~u32 }
+v9::decl_property! { P14: ~u32 }
+v9::decl_property! { P15: ~u32 }
+v9::decl_property! { P16: ~u32 }
+v9::decl_property! { P17: ~u32 }
+v9::decl_property! { P18: ~u32 }
+v9::decl_property! { P19: ~u32 }
+
+#[test]
+fn grouped_args() {
+    let mut u = Universe::new();
+    P00::register(&mut u); P01::register(&mut u); P02::register(&mut u); P03::register(&mut u);
+    P04::register(&mut u); P05::register(&mut u); P06::register(&mut u); P07::register(&mut u);
+    P08::register(&mut u); P09::register(&mut u); P10::register(&mut u); P11::register(&mut u);
+    P12::register(&mut u); P13::register(&mut u); P14::register(&mut u); P15::register(&mut u);
+    P16::register(&mut u); P17::register(&mut u); P18::register(&mut u); P19::register(&mut u);
+    type Left<'a> = (
+        (&'a mut P00, &'a mut P01, &'a mut P02, &'a mut P03, &'a mut P04),
+        (&'a mut P05, &'a mut P06, &'a mut P07, &'a mut P08, &'a mut P09),
+    );
+    type Right<'a> = (
+        (&'a mut P10, &'a mut P11, &'a mut P12, &'a mut P13, &'a mut P14),
+        (&'a P15, &'a P16, &'a P17, &'a P18, &'a P19),
+    );
+    let mut k = Kernel::new(|left: Left, right: Right| {
+        let ((a, b, c, d, e), (f, g, h, i, j)) = left;
+        let ((k, l, m, n, o), (p, q, r, s, t)) = right;
+        fn bump(x: &mut u32) { *x += 1; }
+        bump(a); bump(b); bump(c); bump(d); bump(e);
+        bump(f); bump(g); bump(h); bump(i); bump(j);
+        bump(k); bump(l); bump(m); bump(n); bump(o);
+        assert_eq!(**p + **q + **r + **s + **t, 0);
+    });
+    assert_eq!(k.resources().len(), 20);
+    u.run(&mut k);
+    u.eval(|(a, b): (&P00, &P14), c: &P15| {
+        assert_eq!((**a, **b, **c), (1, 1, 0));
+    });
+}
+
+#[v9::context]
+pub struct Sixteen {
+    pub p00: &mut P00, pub p01: &mut P01, pub p02: &mut P02, pub p03: &mut P03,
+    pub p04: &mut P04, pub p05: &mut P05, pub p06: &mut P06, pub p07: &mut P07,
+    pub p08: &mut P08, pub p09: &mut P09, pub p10: &mut P10, pub p11: &mut P11,
+    pub p12: &mut P12, pub p13: &mut P13, pub p14: &mut P14, pub p15: &mut P15,
+}
+
+#[test]
+fn sixteen_args() {
+    let mut u = Universe::new();
+    P00::register(&mut u); P01::register(&mut u); P02::register(&mut u); P03::register(&mut u);
+    P04::register(&mut u); P05::register(&mut u); P06::register(&mut u); P07::register(&mut u);
+    P08::register(&mut u); P09::register(&mut u); P10::register(&mut u); P11::register(&mut u);
+    P12::register(&mut u); P13::register(&mut u); P14::register(&mut u); P15::register(&mut u);
+    let mut k = Kernel::new(|ctx: Sixteen| {
+        **ctx.p00 += 1;
+        **ctx.p15 += 1;
+    });
+    assert_eq!(k.resources().len(), 16);
+    u.run(&mut k);
+    // Past the old limit of 15 without grouping.
+    u.eval(|
+        a: &P00, b: &P01, c: &P02, d: &P03, e: &P04, f: &P05, g: &P06, h: &P07,
+        i: &P08, j: &P09, k: &P10, l: &P11, m: &P12, n: &P13, o: &P14, p: &P15,
+    | {
+        let mid = **b + **c + **d + **e + **f + **g + **h + **i + **j + **k + **l + **m + **n + **o;
+        assert_eq!((**a, mid, **p), (1, 0, 1));
+    });
+}
+
+#[test]
+fn eval_async() {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    /// Pending once, so that `block_on` has to park and get woken.
+    struct YieldOnce(bool);
+    impl Future for YieldOnce {
+        type Output = ();
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+            if self.0 {
+                return Poll::Ready(());
+            }
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+    async fn kernel(c: &mut COUNTER) -> u32 {
+        **c += 1;
+        YieldOnce(false).await;
+        **c += 1;
+        **c
+    }
+    let mut u = Universe::new();
+    COUNTER::register(&mut u);
+    let got = u.eval_async(kernel);
+    assert_eq!(got, 2);
+    u.eval(|c: &COUNTER| assert_eq!(**c, 2));
+}
+
+#[test]
+fn lock_telemetry() {
+    use std::sync::{Arc, Mutex, mpsc};
+    use std::time::Duration;
+    let waits = Arc::new(Mutex::new(Vec::<(String, Duration)>::new()));
+    let mut u = Universe::new();
+    COUNTER::register(&mut u);
+    {
+        let waits = waits.clone();
+        u.set_lock_telemetry(move |name, waited| waits.lock().unwrap().push((name.to_string(), waited)));
+    }
+    let u = Arc::new(u);
+    let (inside, entered) = mpsc::channel();
+    let holder = {
+        let u = u.clone();
+        std::thread::spawn(move || {
+            u.eval(|_c: &mut COUNTER| {
+                inside.send(()).unwrap();
+                std::thread::sleep(Duration::from_millis(50));
+            });
+        })
+    };
+    entered.recv().unwrap();
+    u.eval(|c: &COUNTER| assert_eq!(**c, 0));
+    holder.join().unwrap();
+    let waits = waits.lock().unwrap();
+    assert_eq!(waits.len(), 2);
+    assert!(waits[1].1 >= Duration::from_millis(25), "{:?}", waits);
+    assert!(waits[1].0.contains("lock_telemetry"));
+}
+
+#[test]
+fn try_run() {
+    use std::sync::{Arc, mpsc};
+    let mut u = Universe::new();
+    COUNTER::register(&mut u);
+    let u = Arc::new(u);
+    let (inside, entered) = mpsc::channel();
+    let (finish, finished) = mpsc::channel::<()>();
+    let holder = {
+        let u = u.clone();
+        std::thread::spawn(move || {
+            u.eval(|_c: &mut COUNTER| {
+                inside.send(()).unwrap();
+                finished.recv().unwrap();
+            });
+        })
+    };
+    entered.recv().unwrap();
+    let mut k = Kernel::new(|c: &mut COUNTER| **c += 1);
+    let err = u.try_run(&mut k).unwrap_err();
+    assert_eq!(err.ty, Ty::of::<COUNTER>());
+    finish.send(()).unwrap();
+    holder.join().unwrap();
+    u.try_run(&mut k).unwrap();
+    u.eval(|c: &COUNTER| assert_eq!(**c, 1));
+}
+
+#[test]
+fn run_retry() {
+    use std::sync::{Arc, mpsc};
+    use std::time::Duration;
+    let mut u = Universe::new();
+    COUNTER::register(&mut u);
+    let u = Arc::new(u);
+    let (inside, entered) = mpsc::channel();
+    let holder = {
+        let u = u.clone();
+        std::thread::spawn(move || {
+            u.eval(|_c: &mut COUNTER| {
+                inside.send(()).unwrap();
+                std::thread::sleep(Duration::from_millis(30));
+            });
+        })
+    };
+    entered.recv().unwrap();
+    let mut k = Kernel::new(|c: &mut COUNTER| **c += 1);
+    let err = u.run_retry(&mut k, 1, Duration::from_millis(1)).unwrap_err();
+    assert_eq!(err.ty, Ty::of::<COUNTER>());
+    u.run_retry(&mut k, 1000, Duration::from_millis(1)).unwrap();
+    holder.join().unwrap();
+    u.eval(|c: &COUNTER| assert_eq!(**c, 1));
+}
+
+v9::decl_table! {
+    pub struct apples {
+        pub crunch: u32,
+    }
+}
+
+v9::decl_table! {
+    pub struct pears {
+        pub squish: i64,
+    }
+}
+
+#[test]
+fn resource_names() {
+    let k = Kernel::new(|_apples: apples::read::crunch, _pears: pears::edit::squish| {});
+    let names = k.resource_names();
+    let find = |table: &str, col: &str| names.iter().find(|(n, _)| {
+        n.starts_with("Column<") && n.contains(table) && n.contains(col)
+    }).map(|&(_, acc)| acc);
+    assert_eq!(find("apples", "u32"), Some(Access::Read));
+    assert_eq!(find("pears", "i64"), Some(Access::Write));
+    assert!(names.iter().all(|(n, _)| !n.contains("v9::column::") && !n.contains("::in_v9::")));
+}
+
+#[test]
+fn validate_kernel() {
+    let mut universe = Universe::new();
+    apples::Marker::register(&mut universe);
+    let k = Kernel::new(|_apples: apples::read::crunch, _pears: pears::edit::squish| {});
+    let err = universe.validate_kernel(&k).unwrap_err();
+    assert!(err.contains("pears") && err.contains("i64"), "{}", err);
+    pears::Marker::register(&mut universe);
+    universe.validate_kernel(&k).unwrap();
+}
+
+#[test]
+fn schedule_overlaps_disjoint() {
+    use std::sync::{Arc, Condvar, Mutex};
+    use std::time::Duration;
+    let mut universe = Universe::new();
+    apples::Marker::register(&mut universe);
+    pears::Marker::register(&mut universe);
+    // Each kernel waits for the other to arrive; that can only happen if they overlap.
+    let meeting = Arc::new((Mutex::new(0), Condvar::new()));
+    let meet = |meeting: Arc<(Mutex<i32>, Condvar)>| move || {
+        let (count, condvar) = &*meeting;
+        let mut count = count.lock().unwrap();
+        *count += 1;
+        condvar.notify_all();
+        let (count, timeout) = condvar
+            .wait_timeout_while(count, Duration::from_secs(10), |count| *count < 2)
+            .unwrap();
+        assert!(!timeout.timed_out(), "kernels didn't overlap ({} arrived)", *count);
+    };
+    let (a, b) = (meet(meeting.clone()), meet(meeting));
+    let mut schedule = Schedule::new();
+    schedule.push(Kernel::new(move |_apples: apples::Write| a()));
+    schedule.push(Kernel::new(move |_pears: pears::Write| b()));
+    assert_eq!(schedule.waves(), vec![vec![0, 1]]);
+    schedule.run(&universe);
+}
+
+#[test]
+fn schedule_serializes_conflicts() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    let mut universe = Universe::new();
+    apples::Marker::register(&mut universe);
+    let inside = Arc::new(AtomicUsize::new(0));
+    let mut schedule = Schedule::new();
+    for crunch in 0..2 {
+        let inside = inside.clone();
+        schedule.push(Kernel::new(move |mut apples: apples::Write| {
+            assert_eq!(inside.fetch_add(1, Ordering::SeqCst), 0);
+            std::thread::sleep(Duration::from_millis(20));
+            apples.push(apples::Row { crunch });
+            inside.fetch_sub(1, Ordering::SeqCst);
+        }));
+    }
+    schedule.push(Kernel::new(|_apples: apples::Read| {}));
+    assert_eq!(schedule.waves(), vec![vec![0], vec![1], vec![2]]);
+    schedule.run(&universe);
+    universe.eval(|apples: apples::Read| {
+        let crunch: Vec<u32> = apples.iter().map(|id| apples.crunch[id]).collect();
+        assert_eq!(crunch, vec![0, 1]);
+    });
+}
+
+#[test]
+fn table_in_tuple() {
+    let mut u = Universe::new();
+    apples::Marker::register(&mut u);
+    P00::register(&mut u);
+    fn helper((mut apples, amount): (apples::Write, &P00)) {
+        apples.push(apples::Row { crunch: **amount + 1 });
+    }
+    u.eval(helper);
+    u.eval(|(apples, amount): (apples::Read, &mut P00)| {
+        **amount = apples.len() as u32;
+        assert_eq!(apples.crunch[apples::FIRST], 1);
+    });
+    u.eval(|amount: &P00| assert_eq!(**amount, 1));
+}
+
+#[test]
+fn reader_count() {
+    let mut u = Universe::new();
+    P00::register(&mut u);
+    let ty = Ty::of::<P00>();
+    assert_eq!(u.reader_count(ty), Some(0));
+    assert_eq!(u.reader_count(Ty::of::<P01>()), None);
+    u.eval(|_a: &P00, u: UniverseRef| {
+        assert_eq!(u.reader_count(ty), Some(1));
+        u.eval(|_b: &P00| {
+            assert_eq!(u.reader_count(ty), Some(2));
+        });
+        assert_eq!(u.writer(ty), None);
+    });
+    u.eval(|_a: &mut P00, u: UniverseRef| {
+        assert_eq!(u.writer(ty), Some(std::thread::current().id()));
+        assert_eq!(u.reader_count(ty), Some(0));
+    });
+    assert_eq!(u.reader_count(ty), Some(0));
+}
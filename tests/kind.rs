@@ -0,0 +1,45 @@
+use v9::prelude::*;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Kind {
+    Goblin,
+    Orc,
+    Troll,
+}
+
+v9::decl_table! {
+    #[kind(kind)]
+    pub struct monsters {
+        pub kind: crate::Kind,
+        pub hp: u32,
+    }
+}
+
+#[test]
+fn iter_kind() {
+    let mut universe = Universe::new();
+    monsters::Marker::register(&mut universe);
+    universe.eval(|mut monsters: monsters::Write| {
+        let kinds = [Kind::Orc, Kind::Goblin, Kind::Troll, Kind::Goblin, Kind::Orc, Kind::Goblin];
+        for (hp, &kind) in kinds.iter().enumerate() {
+            monsters.push(monsters::Row { kind, hp: hp as u32 });
+        }
+    });
+    universe.eval(|monsters: monsters::Read, index: &monsters::KindIndex| {
+        let hps = |k| monsters::iter_kind(index, k).map(|id| monsters.hp[id]).collect::<Vec<_>>();
+        assert_eq!(hps(Kind::Goblin), vec![1, 3, 5]);
+        assert_eq!(hps(Kind::Orc), vec![0, 4]);
+        assert_eq!(hps(Kind::Troll), vec![2]);
+    });
+    universe.eval(|mut monsters: monsters::Edit, ids: &monsters::Ids| {
+        for id in ids {
+            if monsters.kind[id] == Kind::Orc {
+                monsters.kind[id] = Kind::Troll;
+            }
+        }
+    });
+    universe.eval(|index: &monsters::KindIndex| {
+        assert_eq!(monsters::iter_kind(index, Kind::Orc).count(), 0);
+        assert_eq!(monsters::iter_kind(index, Kind::Troll).count(), 3);
+    });
+}
@@ -36,3 +36,79 @@ fn patch() {
         println!("{}", weight[id]);
     });
 }
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "columns of different lengths")]
+fn forgot_column() {
+    let mut universe = Universe::new();
+    cheeses::Marker::register(&mut universe);
+    universe.eval(|_ids: &mut cheeses::Ids, mut flaming: v9::column::WriteColumn<cheeses::Marker, bool>| {
+        unsafe {
+            flaming.col.get_mut().data.push(true);
+        }
+    });
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "columns of different lengths")]
+fn extend_one_column() {
+    let mut universe = Universe::new();
+    cheeses::Marker::register(&mut universe);
+    universe.eval(|mut c: cheeses::Write| c.push(cheeses::Row { flaming: true }));
+    // The ids aren't locked, but they're still compared against.
+    universe.eval(|mut flaming: v9::column::WriteColumn<cheeses::Marker, bool>| {
+        flaming.extend_from(vec![false, true].into_iter());
+    });
+}
+
+v9::decl_property! { OTHER: ~u32 }
+
+#[test]
+#[cfg(debug_assertions)]
+fn forgot_column_poisons_table() {
+    use std::panic::{self, AssertUnwindSafe};
+    let mut universe = Universe::new();
+    cheeses::Marker::register(&mut universe);
+    OTHER::register(&mut universe);
+    let r = panic::catch_unwind(AssertUnwindSafe(|| {
+        universe.eval(|_ids: &mut cheeses::Ids, mut flaming: v9::column::WriteColumn<cheeses::Marker, bool>| {
+            unsafe {
+                flaming.col.get_mut().data.push(true);
+            }
+        });
+    }));
+    assert!(r.is_err());
+    // Only the broken table is poisoned; the rest of the universe carries on.
+    universe.eval(|other: &mut OTHER| **other += 1);
+    universe.eval(|other: &OTHER| assert_eq!(**other, 1));
+    assert!(universe.clear_poison(Ty::of::<v9::column::Column<cheeses::Marker, bool>>()));
+}
+
+#[test]
+fn column_with_default() {
+    type Weights<'a> = v9::column::ReadColumn<'a, cheeses::Marker, f32>;
+    let mut universe = Universe::new();
+    cheeses::Marker::register(&mut universe);
+    let first = universe.eval(|mut c: cheeses::Write| c.push(cheeses::Row { flaming: true }));
+    universe.add_column_with_default::<cheeses::Marker, f32>(1.5);
+    universe.eval(|mut weights: v9::column::EditColumn<cheeses::Marker, f32>| {
+        weights[first] = 3.0;
+    });
+    universe.eval(|mut c: cheeses::Write| {
+        c.push(cheeses::Row { flaming: false });
+        c.push(cheeses::Row { flaming: true });
+    });
+    universe.eval(|weights: Weights, ids: &cheeses::Ids| {
+        let weights: Vec<f32> = ids.iter().map(|id| weights[id]).collect();
+        assert_eq!(weights, vec![3.0, 1.5, 1.5]);
+    });
+    // A recycled id gets the default again.
+    universe.eval(|mut c: cheeses::Write| c.remove(first));
+    universe.eval(|mut c: cheeses::Write| c.push(cheeses::Row { flaming: false }));
+    universe.eval(|weights: Weights, ids: &cheeses::Ids| {
+        assert_eq!(ids.len(), 3);
+        assert!(ids.iter().all(|id| weights[id] == 1.5));
+    });
+}
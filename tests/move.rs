@@ -66,6 +66,97 @@ fn moving() {
             warehouses.remove(warehouses::Id::new(0));
         }
     );
+}
+
+#[test]
+fn swap_remove() {
+    let universe = &mut Universe::new();
+    cheeses::Marker::register(universe);
+    warehouses::Marker::register(universe);
+    universe.kmap(|mut warehouses: warehouses::Write, mut cheeses: cheeses::Write| {
+        for x in 0..4 {
+            let warehouse = warehouses.push(warehouses::Row { coordinates: (x, 0), on_fire: false });
+            cheeses.push(cheeses::Row { quantity: x as f64, warehouse, stinky: false });
+        }
+    });
+    universe.kmap(|mut warehouses: warehouses::Write| {
+        warehouses.swap_remove(warehouses::Id::new(1));
+    });
+    universe.kmap(|warehouses: warehouses::Read, cheeses: cheeses::Read| {
+        assert_eq!(warehouses.len(), 3);
+        let xs: Vec<i32> = warehouses.ids().sorted_iter().map(|id| warehouses.coordinates[id].0).collect();
+        assert_eq!(xs, vec![0, 3, 2]);
+        // The cheese in warehouse 1 went with it, and the one in warehouse 3 followed it to 1.
+        let stock: Vec<(f64, i32)> = cheeses
+            .iter()
+            .map(|id| (cheeses.quantity[id], warehouses.coordinates[cheeses.warehouse[id]].0))
+            .collect();
+        assert_eq!(stock, vec![(0.0, 0), (2.0, 2), (3.0, 3)]);
+        assert_eq!(cheeses.warehouse[cheeses::Id::new(3)], warehouses::Id::new(1));
+    });
+}
+
+#[test]
+fn swap_remove_leaves_other_holes() {
+    let universe = &mut Universe::new();
+    cheeses::Marker::register(universe);
+    warehouses::Marker::register(universe);
+    universe.kmap(|mut warehouses: warehouses::Write| {
+        for x in 0..5 {
+            warehouses.push(warehouses::Row { coordinates: (x, 0), on_fire: false });
+        }
+    });
+    universe.kmap(|mut warehouses: warehouses::Write| {
+        warehouses.remove(warehouses::Id::new(0));
+    });
+    universe.kmap(|mut warehouses: warehouses::Write| {
+        warehouses.swap_remove(warehouses::Id::new(2));
+    });
+    universe.kmap(|warehouses: warehouses::Read| {
+        let rows: Vec<(usize, i32)> = warehouses
+            .ids()
+            .sorted_iter()
+            .map(|id| (id.to_usize(), warehouses.coordinates[id].0))
+            .collect();
+        assert_eq!(rows, vec![(1, 1), (2, 4), (3, 3)]);
+    });
+}
 
-    // FIXME: Y'know, we don't actually have a good way to move rows?
+#[test]
+fn move_row() {
+    let universe = &mut Universe::new();
+    cheeses::Marker::register(universe);
+    warehouses::Marker::register(universe);
+    let (w0, w2) = universe.kmap(|mut warehouses: warehouses::Write, mut cheeses: cheeses::Write| {
+        let w0 = warehouses.push(warehouses::Row { coordinates: (0, 0), on_fire: true });
+        warehouses.push(warehouses::Row { coordinates: (1, 0), on_fire: false });
+        let w2 = warehouses.push(warehouses::Row { coordinates: (2, 0), on_fire: false });
+        cheeses.push(cheeses::Row { quantity: 5.0, warehouse: w2, stinky: true });
+        (w0, w2)
+    });
+    universe.kmap(|mut warehouses: warehouses::Write| {
+        warehouses.remove(w0);
+        warehouses.move_row(w2, w0);
+    });
+    universe.kmap(|warehouses: warehouses::Read, cheeses: cheeses::Read| {
+        assert!(warehouses.iter().all(|id| id.uncheck() != w2));
+        assert_eq!(warehouses.coordinates[w0], (2, 0));
+        assert!(!warehouses.on_fire[w0]);
+        assert_eq!(warehouses.len(), 2);
+        let cheese = cheeses.iter().next().unwrap();
+        assert_eq!(cheeses.warehouse[cheese], w0);
+    });
+}
+
+#[test]
+#[should_panic(expected = "isn't free")]
+fn move_row_onto_live() {
+    let universe = &mut Universe::new();
+    cheeses::Marker::register(universe);
+    warehouses::Marker::register(universe);
+    universe.kmap(|mut warehouses: warehouses::Write| {
+        let a = warehouses.push(warehouses::Row { coordinates: (0, 0), on_fire: false });
+        let b = warehouses.push(warehouses::Row { coordinates: (1, 0), on_fire: false });
+        warehouses.move_row(a, b);
+    });
 }
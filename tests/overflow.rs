@@ -0,0 +1,83 @@
+use v9::prelude::*;
+
+v9::decl_table! {
+    #[raw_index(u8)]
+    pub struct bytes {
+        pub val: u8,
+    }
+}
+
+fn fill() -> Universe {
+    let mut universe = Universe::new();
+    bytes::Marker::register(&mut universe);
+    universe.eval(|mut bytes: bytes::Write| {
+        for val in 0..255 {
+            let id = bytes.push(bytes::Row { val });
+            assert_eq!(id.0, val);
+        }
+    });
+    universe
+}
+
+#[test]
+fn up_to_the_limit() {
+    fill();
+}
+
+#[test]
+#[should_panic(expected = "exhausted its id space")]
+fn past_the_limit() {
+    let universe = fill();
+    universe.eval(|mut bytes: bytes::Write| {
+        bytes.push(bytes::Row { val: 255 });
+    });
+}
+
+#[test]
+#[should_panic(expected = "exhausted its id space")]
+fn contiguous_past_the_limit() {
+    let universe = fill();
+    universe.eval(|mut bytes: bytes::Write| {
+        bytes.push_contiguous(vec![bytes::Row { val: 255 }]);
+    });
+}
+
+#[test]
+fn recycled_ids_dont_count() {
+    let universe = fill();
+    universe.eval(|mut bytes: bytes::Write| {
+        bytes.remove(bytes::FIRST);
+    });
+    universe.eval(|mut bytes: bytes::Write| {
+        assert_eq!(bytes.push(bytes::Row { val: 0 }), bytes::FIRST);
+    });
+}
+
+#[test]
+fn reserve() {
+    let mut universe = Universe::new();
+    bytes::Marker::register(&mut universe);
+    universe.eval(|mut bytes: bytes::Write| {
+        bytes.reserve(200);
+        let capacity = bytes.val.col.data().capacity();
+        assert!(capacity >= 200);
+        for val in 0..200 {
+            bytes.push(bytes::Row { val });
+        }
+        assert_eq!(bytes.val.col.data().capacity(), capacity);
+    });
+}
+
+#[test]
+#[should_panic(expected = "exhausted its id space")]
+fn reserve_past_the_limit() {
+    let universe = fill();
+    universe.eval(|mut bytes: bytes::Write| {
+        bytes.remove(bytes::FIRST);
+    });
+    universe.eval(|mut bytes: bytes::Write| {
+        bytes.reserve(1);
+        // One id is free, but that's not enough.
+        bytes.reserve(2);
+    });
+}
@@ -0,0 +1,61 @@
+use v9::prelude_lib::*;
+use v9::column::{EditChunk, ReadColumn};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+v9::decl_table! {
+    pub struct cells {
+        pub energy: u64,
+    }
+}
+
+#[test]
+fn sum() {
+    let mut universe = Universe::new();
+    cells::Marker::register(&mut universe);
+    universe.eval(|mut cells: cells::Write| {
+        for energy in 0..1000 {
+            cells.push(cells::Row { energy });
+        }
+    });
+    universe.eval(|mut cells: cells::Write| {
+        for i in (0..1000).step_by(7) {
+            cells.remove(cells::Id::new(i));
+        }
+    });
+    let serial = universe.eval(|cells: cells::Read| {
+        cells.iter().map(|id| cells.energy[id]).sum::<u64>()
+    });
+    let total = AtomicU64::new(0);
+    universe.run_par(4, |range: cells::Range, (energy, ids): (ReadColumn<cells::Marker, u64>, &cells::Ids)| {
+        let sum: u64 = range.into_iter().filter(|&id| ids.exists(id)).map(|id| energy[id]).sum();
+        total.fetch_add(sum, Ordering::SeqCst);
+    });
+    assert_eq!(total.load(Ordering::SeqCst), serial);
+
+    universe.run_par(3, |range: cells::Range, mut energy: EditChunk<cells::Marker, u64>| {
+        for id in range {
+            energy[id] *= 2;
+        }
+    });
+    let doubled = universe.eval(|cells: cells::Read| {
+        cells.iter().map(|id| cells.energy[id]).sum::<u64>()
+    });
+    assert_eq!(doubled, serial * 2);
+}
+
+#[test]
+#[should_panic(expected = "outside of this chunk")]
+fn out_of_range() {
+    let mut universe = Universe::new();
+    cells::Marker::register(&mut universe);
+    universe.eval(|mut cells: cells::Write| {
+        for energy in 0..10 {
+            cells.push(cells::Row { energy });
+        }
+    });
+    universe.run_par(2, |range: cells::Range, mut energy: EditChunk<cells::Marker, u64>| {
+        if range.start == cells::FIRST {
+            energy[cells::Id::new(9)] = 0;
+        }
+    });
+}
@@ -25,3 +25,19 @@ fn main() {
     assert!(r.is_err());
     println!("I'm fine.");
 }
+
+#[test]
+fn clear_poison() {
+    let mut u = Universe::new();
+    THING::register(&mut u);
+    let r = panic::catch_unwind(AssertUnwindSafe(|| {
+        u.eval(|_thing: &mut THING| {
+            panic!("*gasp!* He's been poisoned!");
+        });
+    }));
+    assert!(r.is_err());
+    assert!(u.clear_poison(v9::prelude_lib::Ty::of::<THING>()));
+    assert!(!u.clear_poison(v9::prelude_lib::Ty::of::<THING>()));
+    u.eval(|thing: &mut THING| **thing = true);
+    u.eval(|thing: &THING| assert!(**thing));
+}
@@ -44,3 +44,95 @@ fn main() {
         assert_eq!(stuff.the_property.val, 52);
     });
 }
+
+#[v9::table]
+pub struct beep {
+    pub pitch: u32,
+    pub volume: u32,
+}
+
+#[v9::context]
+struct Mixed {
+    pub ids: &beep::Ids,
+    pub pitch: beep::read::pitch,
+    pub volume: beep::edit::volume,
+}
+
+#[v9::context]
+struct Clash {
+    pub beeps: beep::Read,
+    pub volume: beep::edit::volume,
+}
+
+#[test]
+fn disjoint_columns() {
+    use v9::prelude_lib::*;
+    let mut universe = Universe::new();
+    beep::Marker::register(&mut universe);
+    universe.kmap(|mut beeps: beep::Write| {
+        beeps.push(beep::Row { pitch: 440, volume: 0 });
+    });
+    universe.kmap(|mut mixed: Mixed| {
+        for id in mixed.ids {
+            mixed.volume[id] = mixed.pitch[id] / 4;
+        }
+    });
+    universe.kmap(|beeps: beep::Read| {
+        assert_eq!(beeps.volume[beep::FIRST], 110);
+    });
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "fields `beeps` (Read) and `volume` (Write) conflict")]
+fn conflicting_context() {
+    use v9::prelude_lib::*;
+    let mut universe = Universe::new();
+    beep::Marker::register(&mut universe);
+    universe.kmap(|_: Clash| {});
+}
+
+decl_property! { SIDE_TABLE: ~u32 = 7; }
+
+#[test]
+fn register_in_eval_mut() {
+    use v9::prelude_lib::*;
+    let mut universe = Universe::new();
+    let got = universe.eval_mut(|universe| {
+        if !universe.has::<SIDE_TABLE>() {
+            SIDE_TABLE::register(universe);
+        }
+        universe.eval(|side: &mut SIDE_TABLE| {
+            **side += 1;
+            **side
+        })
+    });
+    assert_eq!(got, 8);
+    universe.eval(|side: &SIDE_TABLE| assert_eq!(**side, 8));
+}
+
+selected_entity! {
+    SELECTED_BOOP: crate::boop::Marker
+}
+
+#[test]
+fn selected_entity() {
+    use v9::prelude_lib::*;
+    let mut universe = Universe::new();
+    boop::Marker::register(&mut universe);
+    SELECTED_BOOP::register(&mut universe);
+    let id = universe.eval(|mut boops: boop::Write, selected: &mut SELECTED_BOOP| {
+        boops.push(boop::Row { foo: false });
+        let id = boops.push(boop::Row { foo: true });
+        **selected = Some(id);
+        id
+    });
+    universe.eval(|selected: &SELECTED_BOOP, ids: &boop::Ids| {
+        assert_eq!(selected.get_checked(ids).map(|id| id.uncheck()), Some(id));
+    });
+    universe.eval(|mut boops: boop::Write| boops.remove(id));
+    universe.eval(|selected: &SELECTED_BOOP, ids: &boop::Ids| {
+        assert!(selected.is_some());
+        assert!(selected.get_checked(ids).is_none());
+    });
+}
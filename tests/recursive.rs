@@ -93,3 +93,39 @@ fn main() {
     dbg!(&ev.selection.get::<kingdom::Marker>());
     dbg!(&ev.selection.get::<person::Marker>());
 }
+
+#[test]
+fn eval_from_tracker() {
+    use v9::event::{Push, lifestage};
+    use v9::kernel::KernelArg;
+    use v9::object::UniverseRef;
+    let mut universe = Universe::new();
+    continent::Marker::register(&mut universe);
+    kingdom::Marker::register(&mut universe);
+    universe.add_tracker_with_ref_arg::<_, _, Push<continent::Marker, lifestage::LOGICAL>>(
+        |ev: KernelArg<&Push<continent::Marker, lifestage::LOGICAL>>, universe: UniverseRef| {
+            // The kernel that pushed has released its locks, so this is fine.
+            universe.eval(|mut k: kingdom::Write| {
+                for continent in ev.ids.iter() {
+                    k.push(kingdom::Row { id: 0, continent });
+                }
+            });
+        },
+    );
+    universe.eval(|mut c: continent::Write| {
+        c.push(continent::Row { id: 0 });
+        c.push(continent::Row { id: 1 });
+    });
+    universe.eval(|k: kingdom::Read| assert_eq!(k.len(), 2));
+}
+
+#[test]
+#[should_panic(expected = "would deadlock")]
+fn eval_while_held() {
+    use v9::object::UniverseRef;
+    let mut universe = Universe::new();
+    continent::Marker::register(&mut universe);
+    universe.eval(|_c: continent::Read, universe: UniverseRef| {
+        universe.eval(|_c: continent::Write| {});
+    });
+}
@@ -0,0 +1,55 @@
+use v9::prelude::*;
+
+v9::decl_table! {
+    pub struct owners {
+        pub name: &'static str,
+    }
+}
+
+v9::decl_table! {
+    #[on_delete(restrict)]
+    pub struct pets {
+        pub owner: crate::owners::Id,
+    }
+}
+
+fn setup() -> (Universe, owners::Id, owners::Id) {
+    let mut universe = Universe::new();
+    owners::Marker::register(&mut universe);
+    pets::Marker::register(&mut universe);
+    let (alice, bob) = universe.eval(|mut owners: owners::Write, mut pets: pets::Write| {
+        let alice = owners.push(owners::Row { name: "alice" });
+        let bob = owners.push(owners::Row { name: "bob" });
+        pets.push(pets::Row { owner: alice });
+        (alice, bob)
+    });
+    (universe, alice, bob)
+}
+
+#[test]
+fn unreferenced_delete() {
+    let (universe, _alice, bob) = setup();
+    universe.eval(|mut owners: owners::Write| owners.remove(bob));
+    universe.eval(|owners: owners::Read, pets: pets::Read| {
+        assert_eq!(owners.len(), 1);
+        assert_eq!(pets.len(), 1);
+    });
+}
+
+#[test]
+#[should_panic(expected = "still referred to")]
+fn referenced_delete() {
+    let (universe, alice, _bob) = setup();
+    universe.eval(|mut owners: owners::Write| owners.remove(alice));
+}
+
+#[test]
+fn delete_after_referrer() {
+    let (universe, alice, _bob) = setup();
+    universe.eval(|mut pets: pets::Write, ids: &pets::Ids| {
+        let pet = ids.iter().next().unwrap().uncheck();
+        pets.remove(pet);
+    });
+    universe.eval(|mut owners: owners::Write| owners.remove(alice));
+    universe.eval(|owners: owners::Read| assert_eq!(owners.len(), 1));
+}
@@ -105,3 +105,34 @@ fn fuzz() {
         println!("// {:?}\n", ids);
     }
 }
+
+#[test]
+fn load_state() {
+    let u = Universe::new();
+    let mut saved = IdList::<M>::default();
+    let _ = unsafe { saved.recycle_ids_contiguous(6, false) };
+    saved.flush(&u);
+    saved.delete(Id::new(4));
+    saved.flush(&u);
+    saved.delete(Id::new(3));
+    saved.flush(&u);
+    let (live, free) = (saved.live_ids(), saved.free_ids());
+    assert_eq!(free.len(), 2);
+    let mut ids = IdList::<M>::default();
+    ids.load_state(&live, &free, 6).unwrap();
+    assert_eq!(ids.len(), 4);
+    assert_eq!(ids.outer_capacity(), 6);
+    assert!(!ids.exists(Id::new(3)));
+    assert!(ids.exists(Id::new(5)));
+    // The ids come back in the same order as they would have from the saved list.
+    for _ in 0..3 {
+        assert_eq!(ids.next_recycle_id(), saved.next_recycle_id());
+        let a = unsafe { ids.recycle_id(false) };
+        let b = unsafe { saved.recycle_id(false) };
+        assert_eq!(a, b);
+    }
+
+    let mut ids = IdList::<M>::default();
+    assert!(ids.load_state(&live, &free, 7).is_err());
+    assert!(ids.load_state(&live, &live, 8).is_err());
+}
@@ -0,0 +1,82 @@
+use v9::prelude::*;
+use v9::id::RunList;
+
+#[v9::table]
+pub struct rocks {
+    pub weight: u32,
+}
+
+#[test]
+fn iter_selected() {
+    let mut universe = Universe::new();
+    rocks::Marker::register(&mut universe);
+    universe.eval(|mut rocks: rocks::Write| {
+        for weight in 0..10 {
+            rocks.push(rocks::Row { weight });
+        }
+    });
+    universe.eval(|mut rocks: rocks::Write| {
+        rocks.remove(rocks::Id::new(3));
+        rocks.remove(rocks::Id::new(7));
+    });
+    let mut sel = RunList::<rocks::Marker>::new();
+    sel.push_run(rocks::Id::new(2)..=rocks::Id::new(4));
+    sel.push(rocks::Id::new(7));
+    sel.push(rocks::Id::new(9));
+    universe.eval(|rocks: rocks::Read| {
+        let got: Vec<u32> = rocks
+            .iter_selected(&sel)
+            .map(|id| rocks.weight[id])
+            .collect();
+        assert_eq!(got, vec![2, 4, 9]);
+    });
+}
+
+#[test]
+fn iter_selected_recycled() {
+    let mut universe = Universe::new();
+    rocks::Marker::register(&mut universe);
+    universe.eval(|mut rocks: rocks::Write| {
+        for weight in 0..10 {
+            rocks.push(rocks::Row { weight });
+        }
+    });
+    universe.eval(|mut rocks: rocks::Write| {
+        rocks.remove(rocks::Id::new(7));
+        rocks.remove(rocks::Id::new(3));
+        rocks.remove(rocks::Id::new(5));
+    });
+    universe.eval(|mut rocks: rocks::Write| {
+        for weight in 100..102 {
+            rocks.push(rocks::Row { weight });
+        }
+    });
+    let mut sel = RunList::<rocks::Marker>::new();
+    sel.push_run(rocks::Id::new(0)..=rocks::Id::new(20));
+    universe.eval(|rocks: rocks::Read| {
+        let got: Vec<usize> = rocks.iter_selected(&sel).map(|id| id.to_usize()).collect();
+        let mut all: Vec<usize> = rocks.iter().map(|id| id.to_usize()).collect();
+        all.sort();
+        assert_eq!(got.len(), 9);
+        assert_eq!(got, all);
+    });
+}
+
+#[test]
+fn remove_set() {
+    let mut universe = Universe::new();
+    rocks::Marker::register(&mut universe);
+    universe.eval(|mut rocks: rocks::Write| {
+        for weight in 0..10 {
+            rocks.push(rocks::Row { weight });
+        }
+    });
+    let mut sel = RunList::<rocks::Marker>::new();
+    sel.push_run(rocks::Id::new(2)..=rocks::Id::new(5));
+    sel.push(rocks::Id::new(8));
+    universe.eval(|mut rocks: rocks::Write| rocks.remove_set(&sel));
+    universe.eval(|rocks: rocks::Read| {
+        let got: Vec<u32> = rocks.iter().map(|id| rocks.weight[id]).collect();
+        assert_eq!(got, vec![0, 1, 6, 7, 9]);
+    });
+}
@@ -24,3 +24,145 @@ fn serialize_it() {
     };
     println!("{}", serde_json::to_string_pretty(&col).unwrap());
 }
+
+#[cfg(feature = "serde")]
+#[test]
+fn saved_index() {
+    use v9::linkage::{ColumnIndex, SavedIndex};
+    let mut ids = IdList::<M>::default();
+    let universe = Universe::new();
+    let _ = unsafe { ids.recycle_ids_contiguous(4, false) };
+    ids.flush(&universe);
+    let mut col = Column {
+        table_marker: M,
+        data: vec![3u32, 1, 4, 1],
+    };
+    let mut index = ColumnIndex::<M, u32>::default();
+    index.rebuild(&ids, &col);
+    let json = serde_json::to_string(&index.save(&ids, &col)).unwrap();
+    let saved: SavedIndex<M, u32> = serde_json::from_str(&json).unwrap();
+    let mut loaded = ColumnIndex::<M, u32>::default();
+    assert!(loaded.load(saved.clone(), &ids, &col));
+    assert_eq!(loaded.find(1).count(), 2);
+    col.data[3] = 5;
+    let mut stale = ColumnIndex::<M, u32>::default();
+    assert!(!stale.load(saved, &ids, &col));
+    assert_eq!(stale.find(1).count(), 1);
+    assert_eq!(stale.find(5).count(), 1);
+}
+
+v9::decl_table! {
+    pub struct ships {
+        pub name: String,
+    }
+}
+
+v9::decl_table! {
+    pub struct crew {
+        pub name: String,
+        pub ship: crate::ships::Id,
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn table_round_trip() {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use v9::event::{lifestage, Push};
+    use v9::linkage::ForeignIndex;
+
+    let new_universe = || {
+        let mut universe = Universe::new();
+        ships::Marker::register(&mut universe);
+        crew::Marker::register(&mut universe);
+        universe
+    };
+    let universe = new_universe();
+    let (argo, nautilus) = universe.eval(|mut ships: ships::Write, mut crew: crew::Write| {
+        let argo = ships.push(ships::Row { name: "Argo".into() });
+        let nautilus = ships.push(ships::Row { name: "Nautilus".into() });
+        crew.push(crew::Row { name: "Jason".into(), ship: argo });
+        crew.push(crew::Row { name: "Nemo".into(), ship: nautilus });
+        crew.push(crew::Row { name: "Heracles".into(), ship: argo });
+        (argo, nautilus)
+    });
+    universe.eval(|mut crew: crew::Write| {
+        crew.remove(crew::Id::new(1));
+    });
+    let mut ships_json = vec![];
+    ships::Marker::serialize_table(&universe, &mut serde_json::Serializer::new(&mut ships_json)).unwrap();
+    let mut crew_json = vec![];
+    crew::Marker::serialize_table(&universe, &mut serde_json::Serializer::new(&mut crew_json)).unwrap();
+
+    let loaded = new_universe();
+    let loads = Arc::new(AtomicUsize::new(0));
+    let counter = loads.clone();
+    loaded.add_tracker(move |_universe: &Universe, ev: &mut Push<crew::Marker, lifestage::LOAD>| {
+        counter.fetch_add(ev.ids.len(), Ordering::SeqCst);
+    });
+    ships::Marker::deserialize_table(&loaded, &mut serde_json::Deserializer::from_slice(&ships_json)).unwrap();
+    crew::Marker::deserialize_table(&loaded, &mut serde_json::Deserializer::from_slice(&crew_json)).unwrap();
+    assert_eq!(loads.load(Ordering::SeqCst), 2);
+
+    let dump = |universe: &Universe| universe.eval(|crew: crew::Read| {
+        crew.iter().map(|id| (id.uncheck(), crew.clone_row(id))).map(|(id, row)| (id, row.name, row.ship)).collect::<Vec<_>>()
+    });
+    assert_eq!(dump(&loaded), dump(&universe));
+    loaded.eval(|crew: crew::Read, aboard: ForeignIndex<crew::Marker, ships::Marker>| {
+        let names: Vec<&str> = aboard.children(argo).map(|id| crew.name[id].as_str()).collect();
+        assert_eq!(names, vec!["Jason", "Heracles"]);
+        assert!(!aboard.has_children(nautilus));
+        assert_eq!(crew.ids().outer_capacity(), 3);
+    });
+}
+
+v9::decl_table! {
+    pub struct char_list {
+        pub c: char,
+    }
+}
+
+v9::decl_table! {
+    pub struct names {
+        pub slice: crate::char_list::Range,
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn range_column_round_trip() {
+    let new_universe = || {
+        let mut universe = Universe::new();
+        char_list::Marker::register(&mut universe);
+        names::Marker::register(&mut universe);
+        universe
+    };
+    let universe = new_universe();
+    universe.eval(|mut chars: char_list::Write, mut names: names::Write| {
+        for name in &["bob", "fred", "steve"] {
+            let slice = chars.push_contiguous(name.chars().map(|c| char_list::Row { c }));
+            names.push(names::Row { slice });
+        }
+    });
+    let json = serde_json::to_string(&char_list::Range::new(char_list::Id::new(3), char_list::Id::new(7))).unwrap();
+    assert_eq!(json, r#"{"start":3,"end":7}"#);
+
+    let mut chars_json = vec![];
+    char_list::Marker::serialize_table(&universe, &mut serde_json::Serializer::new(&mut chars_json)).unwrap();
+    let mut names_json = vec![];
+    names::Marker::serialize_table(&universe, &mut serde_json::Serializer::new(&mut names_json)).unwrap();
+    let loaded = new_universe();
+    char_list::Marker::deserialize_table(&loaded, &mut serde_json::Deserializer::from_slice(&chars_json)).unwrap();
+    names::Marker::deserialize_table(&loaded, &mut serde_json::Deserializer::from_slice(&names_json)).unwrap();
+
+    let dump = |universe: &Universe| universe.eval(|chars: char_list::Read, names: names::Read| {
+        names.iter().map(|id| {
+            let slice = names.slice[id];
+            (slice, slice.into_iter().map(|c| chars.c[c]).collect::<String>())
+        }).collect::<Vec<_>>()
+    });
+    let loaded_names = dump(&loaded);
+    assert_eq!(loaded_names, dump(&universe));
+    assert_eq!(loaded_names[1].1, "fred");
+}
@@ -0,0 +1,50 @@
+//! `RunList::shrink_to_fit`, measured by counting what's allocated. This is the only test in this
+//! file, so nothing else allocates meanwhile.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicIsize, Ordering};
+use v9::prelude_lib::*;
+
+struct Counting;
+static LIVE_BYTES: AtomicIsize = AtomicIsize::new(0);
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LIVE_BYTES.fetch_add(layout.size() as isize, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE_BYTES.fetch_sub(layout.size() as isize, Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+}
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+#[derive(Debug, Copy, Clone, Default)]
+struct M;
+impl TableMarker for M {
+    const NAME: Name = "M";
+    type RawId = u32;
+    fn header() -> TableHeader { unimplemented!() }
+}
+impl Register for M {
+    fn register(_universe: &mut Universe) { unimplemented!() }
+}
+
+#[test]
+fn shrink_to_fit() {
+    let mut l = RunList::<M>::new();
+    for i in 0..100 {
+        l.push(Id::new(i * 2));
+    }
+    // Popping the runs leaves their room behind.
+    while l.len() > 10 {
+        l.pop();
+    }
+    let runs = l.get_data().to_vec();
+    let before = LIVE_BYTES.load(Ordering::SeqCst);
+    l.shrink_to_fit();
+    let after = LIVE_BYTES.load(Ordering::SeqCst);
+    assert_eq!(l.get_data(), &runs[..]);
+    let run = std::mem::size_of::<[u32; 2]>() as isize;
+    assert!(after <= before - 50 * run, "only {} bytes were freed", before - after);
+}
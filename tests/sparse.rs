@@ -0,0 +1,67 @@
+use v9::prelude::*;
+use v9::column::SparseColumn;
+
+#[v9::table]
+pub struct ships {
+    pub hull: u32,
+}
+
+type Names = SparseColumn<ships::Marker, String>;
+
+#[test]
+fn delete_drops_entry() {
+    let mut universe = Universe::new();
+    ships::Marker::register(&mut universe);
+    universe.add_sparse_column::<ships::Marker, String>();
+    universe.eval(|mut ships: ships::Write, names: &mut Names| {
+        for hull in 0..4 {
+            let id = ships.push(ships::Row { hull });
+            if hull % 2 == 0 {
+                names.insert(id, format!("ship {}", hull));
+            }
+        }
+    });
+    universe.eval(|names: &Names| {
+        assert_eq!(names.len(), 2);
+        assert_eq!(names[ships::Id::new(2)], "ship 2");
+        assert!(names.get(ships::Id::new(1)).is_none());
+    });
+    universe.eval(|mut ships: ships::Write| {
+        ships.remove(ships::Id::new(2));
+    });
+    universe.eval(|names: &Names| {
+        assert_eq!(names.len(), 1);
+        assert!(!names.contains(ships::Id::new(2)));
+        let ids: Vec<_> = names.iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, vec![ships::Id::new(0)]);
+    });
+}
+
+#[v9::table]
+pub struct boats {
+    pub hull: u32,
+    /// Most boats don't have one.
+    #[sparse]
+    pub name: String,
+}
+
+#[test]
+fn sparse_attribute() {
+    let mut universe = Universe::new();
+    boats::Marker::register(&mut universe);
+    universe.eval(|mut boats: boats::Write, names: &mut boats::sparse::name| {
+        for hull in 0..3 {
+            let id = boats.push(boats::Row { hull });
+            if hull != 1 {
+                names.insert(id, format!("boat {}", hull));
+            }
+        }
+    });
+    universe.eval(|mut boats: boats::Write| {
+        boats.remove(boats::Id::new(0));
+    });
+    universe.eval(|names: &boats::sparse::name| {
+        let names: Vec<_> = names.iter().map(|(id, name)| (id, name.as_str())).collect();
+        assert_eq!(names, vec![(boats::Id::new(2), "boat 2")]);
+    });
+}
@@ -0,0 +1,38 @@
+use v9::prelude::*;
+
+/// A table without columns; only the ids matter.
+#[v9::table]
+pub struct active {}
+
+#[v9::table]
+pub struct timers {
+    pub owner: crate::active::Id,
+    pub ticks: u32,
+}
+
+#[test]
+fn tags() {
+    let mut universe = Universe::new();
+    active::Marker::register(&mut universe);
+    timers::Marker::register(&mut universe);
+    universe.eval(|mut active: active::Write, mut timers: timers::Write| {
+        for ticks in 0..3 {
+            let owner = active.push(active::Row {});
+            timers.push(timers::Row { owner, ticks });
+        }
+    });
+    universe.eval(|active: active::Read| {
+        assert_eq!(active.iter().count(), 3);
+    });
+    universe.eval(|mut active: active::Write| {
+        active.remove(active::Id::new(1));
+    });
+    universe.eval(|active: active::Read, timers: timers::Read| {
+        let ids: Vec<u32> = active.iter().map(|id| id.uncheck().0).collect();
+        assert_eq!(ids, vec![0, 2]);
+        // The foreign key took the timer with it.
+        let ticks: Vec<u32> = timers.iter().map(|id| timers.ticks[id]).collect();
+        assert_eq!(ticks, vec![0, 2]);
+    });
+    universe.eval(|_: active::Edit| {});
+}
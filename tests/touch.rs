@@ -0,0 +1,50 @@
+use v9::prelude::*;
+use v9::linkage::TouchLog;
+
+#[v9::table]
+pub struct lamps {
+    pub lit: bool,
+    pub watts: u32,
+}
+
+#[test]
+fn touched() {
+    let mut universe = Universe::new();
+    lamps::Marker::register(&mut universe);
+    lamps::track_touches(&mut universe);
+    universe.eval(|mut lamps: lamps::Write| {
+        for watts in 0..6 {
+            lamps.push(lamps::Row { lit: false, watts });
+        }
+    });
+    universe.eval(|log: &mut TouchLog<lamps::Marker>| {
+        assert_eq!(log.take().len(), 6);
+    });
+    universe.eval(|mut lamps: lamps::Edit, ids: &lamps::Ids| {
+        for id in ids {
+            if lamps.watts[id] % 2 == 0 {
+                lamps.lit[id] = true;
+            }
+        }
+    });
+    universe.eval(|mut lamps: lamps::Write| {
+        lamps.remove(lamps::Id::new(5));
+    });
+    universe.eval(|log: &TouchLog<lamps::Marker>| {
+        let mut touched: Vec<u32> = log.touched.iter().map(|id| id.0).collect();
+        touched.sort();
+        assert_eq!(touched, vec![0, 2, 4, 5]);
+    });
+}
+
+#[test]
+fn edit_log() {
+    let mut universe = Universe::new();
+    lamps::Marker::register(&mut universe);
+    universe.add_edit_log::<lamps::Marker, u32>(16);
+    let id = universe.eval(|mut lamps: lamps::Write| lamps.push(lamps::Row { lit: false, watts: 40 }));
+    universe.eval(|mut watts: lamps::edit::watts| watts[id] = 60);
+    universe.eval(|mut watts: lamps::edit::watts| watts[id] = 100);
+    assert_eq!(universe.drain_edits::<lamps::Marker, u32>(), vec![(id, 40), (id, 60)]);
+    assert!(universe.drain_edits::<lamps::Marker, u32>().is_empty());
+}